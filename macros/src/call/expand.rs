@@ -9,8 +9,8 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// not include the `self`, `runtime: &mut T` or `caller: T::AccountId` parameters, which we
+	// always assume are the first parameters to these calls.
 	let args_name = methods
 		.iter()
 		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
@@ -23,6 +23,13 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a vector of the `runtime` argument to pass to each of the functions in `fn_name`,
+	// which is empty for functions which do not need the rest of the runtime.
+	let runtime_arg = methods
+		.iter()
+		.map(|method| if method.with_runtime { quote!(runtime,) } else { quote!() })
+		.collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
 	// and the `Dispatch` trait logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
@@ -34,25 +41,37 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			// Uses `T` even when no call argument does. It can never be constructed.
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<T>, core::convert::Infallible),
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
+		// appropriate function call with all arguments, including the `caller`, and the `runtime`
+		// for functions which asked for it.
+		impl<T: Config> crate::support::PalletDispatch<T> for #pallet_struct<T> {
 			type Caller = T::AccountId;
 			type Call = Call<T>;
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
+			#[allow(unused_variables)]
+			fn dispatch(
+				&mut self,
+				runtime: &mut T,
+				caller: Self::Caller,
+				call: Self::Call,
+			) -> crate::support::DispatchResult {
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
 							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
+								#runtime_arg
+								// Note that we assume the `caller` comes before the call arguments.
 								caller,
 								#( #args_name ),*
 							)?;
 						},
 					)*
+					Call::__Ignore(_, never) => match never {},
 				}
 				Ok(())
 			}
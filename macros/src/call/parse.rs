@@ -25,6 +25,8 @@ pub struct CallVariantDef {
 	pub name: syn::Ident,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// Whether the function takes a `runtime: &mut T` argument before the `caller`.
+	pub with_runtime: bool,
 }
 
 impl CallDef {
@@ -59,23 +61,31 @@ impl CallDef {
 					},
 				}
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// The second argument can be `runtime: &mut T`, for calls which need to access the other
+				// pallets of the runtime.
+				let with_runtime = match method.sig.inputs.iter().nth(1) {
+					Some(syn::FnArg::Typed(arg)) => check_runtime_arg(arg)?,
+					_ => false,
+				};
+				let caller_index = if with_runtime { 2 } else { 1 };
+
+				// The next argument should be the `caller: T::AccountId` argument.
+				match method.sig.inputs.iter().nth(caller_index) {
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
 						// `caller: T::AccountId`.
 						check_caller_arg(arg)?;
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, expected a `caller: T::AccountId` argument";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
 				}
 
 				let fn_name = method.sig.ident.clone();
 
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
+				// Parsing the rest of the args. Skipping `self`, `caller` and maybe `runtime`.
+				for arg in method.sig.inputs.iter().skip(caller_index + 1) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
 						arg
@@ -96,7 +106,7 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef { name: fn_name, args, with_runtime });
 			}
 		}
 
@@ -105,6 +115,30 @@ impl CallDef {
 	}
 }
 
+/// Check whether an arg is the optional `runtime: &mut T` argument of a call, and if so that it is
+/// exactly `runtime: &mut T`.
+pub fn check_runtime_arg(arg: &syn::PatType) -> syn::Result<bool> {
+	// We also support the name as `_runtime` for when the variable is unused.
+	match &*arg.pat {
+		syn::Pat::Ident(ident) if ident.ident == "runtime" || ident.ident == "_runtime" => {},
+		_ => return Ok(false),
+	}
+
+	// This checks the type is `&mut T`.
+	let is_mut_t = match &*arg.ty {
+		syn::Type::Reference(reference) if reference.mutability.is_some() => {
+			matches!(&*reference.elem, syn::Type::Path(tp) if tp.path.is_ident("T"))
+		},
+		_ => false,
+	};
+	if !is_mut_t {
+		let msg = "Invalid type for runtime parameter: expected `runtime: &mut T`";
+		return Err(syn::Error::new(arg.ty.span(), msg))
+	}
+
+	Ok(true)
+}
+
 /// Check caller arg is exactly: `caller: T::AccountId`.
 ///
 /// This is kept strict to keep the code simple.
@@ -1,6 +1,16 @@
 mod call;
 mod runtime;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates code needed for dispatching calls to the functions of the `impl` block:
+/// - `enum Call` - an enum with a variant for each function, holding its arguments, and a hidden
+///   `__Ignore` variant which cannot be constructed, using `T` even when no argument does.
+/// - implements the trait `support::PalletDispatch` to route a `Call` to its function.
+///
+/// Every function takes `&mut self` and then `caller: T::AccountId`, followed by its own arguments.
+/// Functions which need to access the other pallets of the runtime can also take
+/// `runtime: &mut T` right before the `caller`.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -15,8 +25,34 @@ pub fn call(
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
-///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   basic actions like incrementing the block number, checking the block to be executed has a
+///   valid block number and clearing the events of the previous block. It returns a receipt for
+///   each extrinsic, with the storage accesses made while dispatching it. Once all the extrinsics
+///   are applied, it calls the `support::Hooks::on_finalize` hook of every pallet, whose storage
+///   changes are not part of the receipts nor of the changeset of any extrinsic. Finally, the
+///   system pallet records the hash of the block, which commits to all the storage changes made
+///   in it. Storage writes made outside of a block, e.g. at genesis, keep no history.
+///
+///   Two limitations apply to every call and hook run this way:
+///   - An extrinsic which fails keeps everything it wrote before returning its error, as nothing
+///     is rolled back, starting with the nonce of its caller. Calls must run all their checks
+///     before their first write.
+///   - The pallet of a call or a hook is taken out of the runtime while it runs, and replaced by an
+///     empty one. Going through the runtime to reach that same pallet, e.g. with `AsRef`, sees no
+///     state at all, so a pallet must use `self` to access its own storage.
+/// - `fn execute_block_with_changeset()` - which executes a block like `execute_block()`, and also
+///   returns every storage key written or removed by each extrinsic, with its old and new value.
+/// - `fn execute_block_inspecting()` - which executes a block like `execute_block()`, calling a
+///   closure with the runtime and the receipt of every extrinsic right after it is applied, e.g. to
+///   check invariants.
+/// - `fn state_at()` - which runs a query against the state of the runtime as of an earlier block,
+///   as long as that block has not been pruned.
+///
+/// It also implements `AsRef` and `AsMut` for every pallet, so pallets can access each other
+/// through the runtime.
+///
+/// Every pallet must implement `support::Hooks`, with an empty `impl` if it does not need any, and
+/// `support::storage::Prune`, pruning each of its storage items at the start of every block.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
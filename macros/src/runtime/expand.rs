@@ -25,21 +25,141 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 			}
 
 			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			//
+			// Returns a receipt for every extrinsic, including the storage accesses it made. A
+			// failed extrinsic keeps whatever it wrote before failing, as nothing is rolled back.
+			fn execute_block(
+				&mut self,
+				block: types::Block,
+			) -> Result<Vec<crate::support::Receipt>, &'static str> {
+				self.apply_block(block, None, |_, _| {})
+			}
+
+			// Execute a block of extrinsics like `execute_block`, calling `inspect` with the
+			// runtime and the receipt of every extrinsic, right after it is applied.
+			fn execute_block_inspecting(
+				&mut self,
+				block: types::Block,
+				inspect: impl FnMut(&Self, &crate::support::Receipt),
+			) -> Result<Vec<crate::support::Receipt>, &'static str> {
+				self.apply_block(block, None, inspect)
+			}
+
+			// Execute a block of extrinsics like `execute_block`, and also return every storage
+			// change made by each extrinsic.
+			fn execute_block_with_changeset(
+				&mut self,
+				block: types::Block,
+			) -> Result<(Vec<crate::support::Receipt>, crate::support::storage::Changeset), &'static str> {
+				let mut changeset = crate::support::storage::Changeset::default();
+				let receipts = self.apply_block(block, Some(&mut changeset), |_, _| {})?;
+				Ok((receipts, changeset))
+			}
+
+			// Apply all the extrinsics of a block, recording their storage changes in `changeset`
+			// if one is given, and calling `inspect` after each of them. Then finalize the block.
+			fn apply_block(
+				&mut self,
+				block: types::Block,
+				mut changeset: Option<&mut crate::support::storage::Changeset>,
+				mut inspect: impl FnMut(&Self, &crate::support::Receipt),
+			) -> Result<Vec<crate::support::Receipt>, &'static str> {
 				self.system.inc_block_number();
 				if block.header.block_number != self.system.block_number() {
 					return Err(&"block number does not match what is expected")
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+				self.system.reset_events();
+				self.system.reset_digest();
+				let number = block.header.block_number.into();
+				crate::support::storage::begin_block(number);
+				// Forget the history of storage which is only needed to read pruned blocks.
+				let earliest = <Self as system::Config>::PRUNING.earliest(number);
+				crate::support::storage::Prune::prune(&mut self.system, earliest);
+				#(
+					crate::support::storage::Prune::prune(&mut self.#pallet_names, earliest);
+				)*
+				// Every change made in the block is recorded, so that its hash commits to them.
+				let (receipts, changes) = crate::support::storage::recorded(|| {
+					let mut receipts = Vec::with_capacity(block.extrinsics.len());
+					for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
+						let (result, storage) = match changeset.as_deref_mut() {
+							Some(changeset) => {
+								let (outcome, changes) = crate::support::storage::recorded(|| {
+									self.apply_extrinsic(caller, call)
+								});
+								changeset.extrinsics.push(changes);
+								outcome
+							},
+							None => self.apply_extrinsic(caller, call),
+						};
+						if let Err(e) = result {
+							eprintln!(
+								"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+								block.header.block_number, i, e
+							)
+						}
+						let receipt = crate::support::Receipt { extrinsic_index: i, result, storage };
+						inspect(self, &receipt);
+						receipts.push(receipt);
+					}
+					self.on_finalize();
+					receipts
+				});
+				self.finalize_block(&changes);
+				Ok(receipts)
+			}
+
+			// Call the `on_finalize` hook of every pallet, at the end of the block. The pallet is
+			// taken out of the runtime while it runs, so it can be given the rest of the runtime.
+			fn on_finalize(&mut self) {
+				#(
+					let mut pallet = core::mem::replace(
+						&mut self.#pallet_names,
+						<#pallet_types>::new(),
+					);
+					crate::support::Hooks::on_finalize(&mut pallet, self);
+					self.#pallet_names = pallet;
+				)*
+			}
+
+			// Record the hash of the block, once nothing can be logged in its digest nor changed in
+			// storage anymore, and end the block, so that later writes are not attributed to it.
+			fn finalize_block(&mut self, changes: &crate::support::storage::PalletChanges) {
+				self.system.finalize_block(changes);
+				crate::support::storage::end_block();
+			}
+
+			// Run `f` on the runtime as it was at the end of block `block_number`.
+			//
+			// Only the pallets' storage maps are read as of that block. Fails if the block has not
+			// been executed yet, or if its state has been pruned.
+			fn state_at<R>(
+				&self,
+				block_number: types::BlockNumber,
+				f: impl FnOnce(&Self) -> R,
+			) -> Result<R, &'static str> {
+				let latest = self.system.block_number().into();
+				let number = block_number.into();
+				if number > latest {
+					return Err(&"block has not been executed yet")
 				}
-				Ok(())
+				if number < <Self as system::Config>::PRUNING.earliest(latest) {
+					return Err(&"state of the block has been pruned")
+				}
+				Ok(crate::support::storage::read_at(number, || f(self)))
+			}
+
+			// Apply a single extrinsic: increment the nonce of the caller and dispatch the call,
+			// metering the storage accesses of both.
+			fn apply_extrinsic(
+				&mut self,
+				caller: <Self as crate::support::Dispatch>::Caller,
+				call: RuntimeCall,
+			) -> (crate::support::DispatchResult, crate::support::storage::StorageMeter) {
+				crate::support::storage::metered(|| {
+					self.system.inc_nonce(&caller);
+					self.dispatch(caller, call)
+				})
 			}
 		}
 	};
@@ -73,13 +193,50 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							// The pallet is taken out of the runtime while it dispatches the call,
+							// so it can be given the rest of the runtime.
+							let mut pallet = core::mem::replace(
+								&mut self.#pallet_names,
+								<#pallet_types>::new(),
+							);
+							let result = crate::support::PalletDispatch::dispatch(
+								&mut pallet,
+								self,
+								caller,
+								call,
+							);
+							self.#pallet_names = pallet;
+							result?;
 						}
 					),*
 				}
 				Ok(())
 			}
 		}
+
+		// Give access to each pallet through the runtime, including system.
+		impl AsRef<system::Pallet<#runtime_struct>> for #runtime_struct {
+			fn as_ref(&self) -> &system::Pallet<#runtime_struct> {
+				&self.system
+			}
+		}
+		impl AsMut<system::Pallet<#runtime_struct>> for #runtime_struct {
+			fn as_mut(&mut self) -> &mut system::Pallet<#runtime_struct> {
+				&mut self.system
+			}
+		}
+		#(
+			impl AsRef<#pallet_types> for #runtime_struct {
+				fn as_ref(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+			}
+			impl AsMut<#pallet_types> for #runtime_struct {
+				fn as_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
 	};
 
 	// We combine and return all the generated code.
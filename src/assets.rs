@@ -0,0 +1,510 @@
+use crate::support::storage::{Encode, Prune, StorageMap};
+use crate::support::DispatchResult;
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use std::collections::BTreeMap;
+
+pub trait Config: crate::system::Config {
+    /// The type used to identify an asset.
+    type AssetId: Debug + Ord + Copy + Encode;
+    /// The type used to represent an amount of an asset.
+    type AssetBalance: Debug + Zero + Copy + CheckedSub + CheckedAdd + PartialOrd + Encode;
+}
+
+/// The details of an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetDetails<AccountId, Balance> {
+    /// The account allowed to mint, burn, freeze and thaw the asset, and to set its metadata.
+    pub admin: AccountId,
+    /// The amount of the asset held by all accounts.
+    pub supply: Balance,
+    /// The minimum balance of the asset an account must hold. An account left with less is
+    /// removed, and its remaining balance is destroyed.
+    pub min_balance: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for AssetDetails<AccountId, Balance> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.admin.encode();
+        bytes.extend(self.supply.encode());
+        bytes.extend(self.min_balance.encode());
+        bytes
+    }
+}
+
+/// The balance of an account for an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetAccount<Balance> {
+    /// The amount of the asset held by the account.
+    pub balance: Balance,
+    /// Whether the account is frozen, so it cannot transfer the asset.
+    pub is_frozen: bool,
+}
+
+impl<Balance: Encode> Encode for AssetAccount<Balance> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.balance.encode();
+        bytes.extend(self.is_frozen.encode());
+        bytes
+    }
+}
+
+/// The metadata describing an asset to users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetMetadata {
+    /// The name of the asset.
+    pub name: String,
+    /// The ticker symbol of the asset.
+    pub symbol: String,
+    /// The number of decimals used to display balances of the asset.
+    pub decimals: u8,
+}
+
+impl Encode for AssetMetadata {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.name.encode();
+        bytes.extend(self.symbol.encode());
+        bytes.extend(self.decimals.encode());
+        bytes
+    }
+}
+
+/// The key of the balance of an account for an asset.
+type AccountKey<T> = (
+    <T as Config>::AssetId,
+    <T as crate::system::Config>::AccountId,
+);
+
+/// This is the Assets Module.
+/// It allows accounts to create their own fungible assets, next to the native balance.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The details of each asset.
+    assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::AssetBalance>>,
+    /// The balance of each account for each asset.
+    accounts: StorageMap<AccountKey<T>, AssetAccount<T::AssetBalance>>,
+    /// The metadata of each asset, if set by its admin.
+    metadata: StorageMap<T::AssetId, AssetMetadata>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Create the asset `id`, administered by `admin`.
+    /// Accounts must hold at least `min_balance` of the asset, which must not be zero.
+    pub fn create(
+        &mut self,
+        _caller: T::AccountId,
+        id: T::AssetId,
+        admin: T::AccountId,
+        min_balance: T::AssetBalance,
+    ) -> DispatchResult {
+        if self.assets.contains_key(&id) {
+            return Err("Asset already exists.");
+        }
+        if min_balance.is_zero() {
+            return Err("Minimum balance must not be zero.");
+        }
+
+        let details = AssetDetails {
+            admin,
+            supply: T::AssetBalance::zero(),
+            min_balance,
+        };
+        self.assets.insert(id, details);
+        Ok(())
+    }
+
+    /// Create `amount` of the asset `id` in the account `beneficiary`. Admin only.
+    pub fn mint(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        beneficiary: T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        let mut details = self.ensure_admin(id, &caller)?;
+        let new_bal = self
+            .balance(id, &beneficiary)
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+
+        if new_bal < details.min_balance {
+            return Err("Recipient would be left below the minimum balance.");
+        }
+
+        details.supply = details
+            .supply
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+        self.assets.insert(id, details);
+        self.set_balance(id, &beneficiary, new_bal);
+        Ok(())
+    }
+
+    /// Destroy `amount` of the asset `id` from the account `who`, removing it if it is left below
+    /// the minimum balance. Admin only.
+    pub fn burn(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        who: T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        let mut details = self.ensure_admin(id, &caller)?;
+        let new_bal = self
+            .balance(id, &who)
+            .checked_sub(&amount)
+            .ok_or("Not enough funds.")?;
+
+        details.supply = details
+            .supply
+            .checked_sub(&amount)
+            .ok_or("over/underflow occured.")?;
+        self.settle_balance(id, details, &who, new_bal)
+    }
+
+    /// Transfer `amount` of the asset `id` from the `caller` to `target`.
+    /// The `caller` is removed if it is left below the minimum balance, but `target` must end up
+    /// with at least the minimum balance.
+    pub fn transfer(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        target: T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        let details = self.asset(id).ok_or("Asset does not exist.")?;
+        if self.is_frozen(id, &caller) {
+            return Err("Account is frozen.");
+        }
+
+        let new_caller_bal = self
+            .balance(id, &caller)
+            .checked_sub(&amount)
+            .ok_or("Not enough funds.")?;
+        if caller == target {
+            return Ok(());
+        }
+
+        let new_target_bal = self
+            .balance(id, &target)
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+        if new_target_bal < details.min_balance {
+            return Err("Recipient would be left below the minimum balance.");
+        }
+
+        self.settle_balance(id, details, &caller, new_caller_bal)?;
+        self.set_balance(id, &target, new_target_bal);
+        Ok(())
+    }
+
+    /// Prevent `who` from transferring the asset `id`. Admin only.
+    pub fn freeze(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        who: T::AccountId,
+    ) -> DispatchResult {
+        self.ensure_admin(id, &caller)?;
+        self.set_frozen(id, who, true)
+    }
+
+    /// Allow `who` to transfer the asset `id` again. Admin only.
+    pub fn thaw(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        who: T::AccountId,
+    ) -> DispatchResult {
+        self.ensure_admin(id, &caller)?;
+        self.set_frozen(id, who, false)
+    }
+
+    /// Set the metadata of the asset `id`. Admin only.
+    pub fn set_metadata(
+        &mut self,
+        caller: T::AccountId,
+        id: T::AssetId,
+        name: String,
+        symbol: String,
+        decimals: u8,
+    ) -> DispatchResult {
+        self.ensure_admin(id, &caller)?;
+        let metadata = AssetMetadata {
+            name,
+            symbol,
+            decimals,
+        };
+        self.metadata.insert(id, metadata);
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+impl<T: Config> Prune for Pallet<T> {
+    fn prune(&mut self, earliest: u64) {
+        self.assets.prune(earliest);
+        self.accounts.prune(earliest);
+        self.metadata.prune(earliest);
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the assets module.
+    pub fn new() -> Self {
+        Self {
+            assets: StorageMap::new("assets", "assets"),
+            accounts: StorageMap::new("assets", "accounts"),
+            metadata: StorageMap::new("assets", "metadata"),
+        }
+    }
+
+    /// The details of the asset `id`, if it exists.
+    pub fn asset(&self, id: T::AssetId) -> Option<AssetDetails<T::AccountId, T::AssetBalance>> {
+        self.assets.get(&id).cloned()
+    }
+
+    /// The metadata of the asset `id`, if set.
+    pub fn metadata(&self, id: T::AssetId) -> Option<&AssetMetadata> {
+        self.metadata.get(&id)
+    }
+
+    /// The balance of `who` for the asset `id`.
+    pub fn balance(&self, id: T::AssetId, who: &T::AccountId) -> T::AssetBalance {
+        self.accounts
+            .get(&(id, who.clone()))
+            .map(|account| account.balance)
+            .unwrap_or_else(T::AssetBalance::zero)
+    }
+
+    /// Whether `who` is frozen for the asset `id`.
+    pub fn is_frozen(&self, id: T::AssetId, who: &T::AccountId) -> bool {
+        self.accounts
+            .get(&(id, who.clone()))
+            .is_some_and(|account| account.is_frozen)
+    }
+
+    /// Check that the balances of all accounts for each asset add up to its supply.
+    pub fn check_invariants(&self) -> DispatchResult {
+        let mut totals = BTreeMap::new();
+        for ((id, _), account) in self.accounts.iter_from(None) {
+            let total = totals.entry(*id).or_insert_with(T::AssetBalance::zero);
+            *total = total
+                .checked_add(&account.balance)
+                .ok_or("sum of asset balances overflows")?;
+        }
+        for (id, details) in self.assets.iter_from(None) {
+            let total = totals.remove(id).unwrap_or_else(T::AssetBalance::zero);
+            if total != details.supply {
+                return Err("sum of asset balances does not match the supply");
+            }
+        }
+        if !totals.is_empty() {
+            return Err("balance held for an asset which does not exist");
+        }
+        Ok(())
+    }
+
+    /// Get the details of the asset `id`, checking that `who` is its admin.
+    fn ensure_admin(
+        &self,
+        id: T::AssetId,
+        who: &T::AccountId,
+    ) -> Result<AssetDetails<T::AccountId, T::AssetBalance>, &'static str> {
+        let details = self.asset(id).ok_or("Asset does not exist.")?;
+        if details.admin != *who {
+            return Err("caller is not the asset admin");
+        }
+        Ok(details)
+    }
+
+    /// Set the balance of `who` for the asset `id`, without checking the minimum balance or
+    /// updating the supply.
+    fn set_balance(&mut self, id: T::AssetId, who: &T::AccountId, amount: T::AssetBalance) {
+        let key = (id, who.clone());
+        let is_frozen = self.is_frozen(id, who);
+        self.accounts.insert(
+            key,
+            AssetAccount {
+                balance: amount,
+                is_frozen,
+            },
+        );
+    }
+
+    /// Set the balance of `who` for the asset `id`, removing the account if it is left below the
+    /// minimum balance. The supply is reduced by the dust destroyed, and `details` are stored.
+    fn settle_balance(
+        &mut self,
+        id: T::AssetId,
+        mut details: AssetDetails<T::AccountId, T::AssetBalance>,
+        who: &T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        if amount < details.min_balance {
+            details.supply = details
+                .supply
+                .checked_sub(&amount)
+                .ok_or("over/underflow occured.")?;
+            self.accounts.remove(&(id, who.clone()));
+        } else {
+            self.set_balance(id, who, amount);
+        }
+        self.assets.insert(id, details);
+        Ok(())
+    }
+
+    /// Set whether `who` is frozen for the asset `id`.
+    fn set_frozen(&mut self, id: T::AssetId, who: T::AccountId, is_frozen: bool) -> DispatchResult {
+        let key = (id, who);
+        let mut account = self
+            .accounts
+            .get(&key)
+            .cloned()
+            .ok_or("Account does not hold the asset.")?;
+        account.is_frozen = is_frozen;
+        self.accounts.insert(key, account);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    struct TestConfig;
+
+    impl crate::system::Config for TestConfig {
+        type AccountId = &'static str;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        const PRUNING: crate::support::storage::Pruning = crate::support::storage::Pruning::Archive;
+        const BLOCK_HASH_COUNT: u64 = 256;
+    }
+
+    impl super::Config for TestConfig {
+        type AssetId = u32;
+        type AssetBalance = u64;
+    }
+
+    #[test]
+    fn create_mint_and_burn() {
+        let mut assets = super::Pallet::<TestConfig>::new();
+
+        assert_eq!(
+            assets.create("alice", 1, "admin", 0),
+            Err("Minimum balance must not be zero.")
+        );
+        assert_eq!(assets.create("alice", 1, "admin", 10), Ok(()));
+        assert_eq!(
+            assets.create("bob", 1, "bob", 10),
+            Err("Asset already exists.")
+        );
+
+        // Only the admin can mint and burn, and accounts need the minimum balance.
+        assert_eq!(
+            assets.mint("alice", 1, "bob", 100),
+            Err("caller is not the asset admin")
+        );
+        assert_eq!(
+            assets.mint("admin", 2, "bob", 100),
+            Err("Asset does not exist.")
+        );
+        assert_eq!(
+            assets.mint("admin", 1, "bob", 5),
+            Err("Recipient would be left below the minimum balance.")
+        );
+        assert_eq!(assets.mint("admin", 1, "bob", 100), Ok(()));
+        assert_eq!(assets.balance(1, &"bob"), 100);
+        assert_eq!(assets.asset(1).unwrap().supply, 100);
+
+        assert_eq!(
+            assets.burn("admin", 1, "bob", 101),
+            Err("Not enough funds.")
+        );
+        assert_eq!(assets.burn("admin", 1, "bob", 40), Ok(()));
+        assert_eq!(assets.asset(1).unwrap().supply, 60);
+
+        // The dust left by a burn is destroyed.
+        assert_eq!(assets.burn("admin", 1, "bob", 55), Ok(()));
+        assert_eq!(assets.balance(1, &"bob"), 0);
+        assert_eq!(assets.asset(1).unwrap().supply, 0);
+    }
+
+    #[test]
+    fn transfer_freeze_and_thaw() {
+        let mut assets = super::Pallet::<TestConfig>::new();
+        assets.create("alice", 1, "admin", 10).unwrap();
+        assets.mint("admin", 1, "alice", 100).unwrap();
+
+        assert_eq!(
+            assets.transfer("alice", 1, "bob", 101),
+            Err("Not enough funds.")
+        );
+        assert_eq!(
+            assets.transfer("alice", 1, "bob", 5),
+            Err("Recipient would be left below the minimum balance.")
+        );
+        assert_eq!(assets.transfer("alice", 1, "bob", 60), Ok(()));
+        assert_eq!(assets.transfer("alice", 1, "alice", 40), Ok(()));
+        assert_eq!(assets.balance(1, &"alice"), 40);
+        assert_eq!(assets.balance(1, &"bob"), 60);
+
+        assert_eq!(
+            assets.freeze("alice", 1, "bob"),
+            Err("caller is not the asset admin")
+        );
+        assert_eq!(
+            assets.freeze("admin", 1, "charlie"),
+            Err("Account does not hold the asset.")
+        );
+        assert_eq!(assets.freeze("admin", 1, "bob"), Ok(()));
+        assert!(assets.is_frozen(1, &"bob"));
+        assert_eq!(
+            assets.transfer("bob", 1, "alice", 10),
+            Err("Account is frozen.")
+        );
+        // A frozen account can still receive the asset.
+        assert_eq!(assets.transfer("alice", 1, "bob", 10), Ok(()));
+        assert_eq!(assets.thaw("admin", 1, "bob"), Ok(()));
+
+        // `alice` is removed when left with dust, which is destroyed.
+        assert_eq!(assets.transfer("alice", 1, "bob", 25), Ok(()));
+        assert_eq!(assets.balance(1, &"alice"), 0);
+        assert_eq!(assets.balance(1, &"bob"), 95);
+        assert_eq!(assets.asset(1).unwrap().supply, 95);
+        assert_eq!(assets.check_invariants(), Ok(()));
+
+        assets.accounts.insert(
+            (1, "bob"),
+            super::AssetAccount {
+                balance: 96,
+                is_frozen: false,
+            },
+        );
+        assert_eq!(
+            assets.check_invariants(),
+            Err("sum of asset balances does not match the supply")
+        );
+    }
+
+    #[test]
+    fn set_metadata() {
+        let mut assets = super::Pallet::<TestConfig>::new();
+        assets.create("alice", 1, "admin", 1).unwrap();
+
+        let result = assets.set_metadata("alice", 1, "Token".to_string(), "TOK".to_string(), 12);
+        assert_eq!(result, Err("caller is not the asset admin"));
+        assert_eq!(assets.metadata(1), None);
+
+        let result = assets.set_metadata("admin", 1, "Token".to_string(), "TOK".to_string(), 12);
+        assert_eq!(result, Ok(()));
+        let metadata = assets.metadata(1).unwrap();
+        assert_eq!(
+            (metadata.name.as_str(), metadata.symbol.as_str()),
+            ("Token", "TOK")
+        );
+        assert_eq!(metadata.decimals, 12);
+    }
+}
@@ -1,16 +1,164 @@
+use crate::support::currency::{
+    BalanceStatus, Currency, FreezableCurrency, HoldableCurrency, LockIdentifier, LockableCurrency,
+    Reasons, ReservableCurrency,
+};
+use crate::support::storage::{Encode, Page, Prune, StorageMap, StorageValue};
+use crate::support::DispatchResult;
+use core::fmt::Debug;
 use num::traits::{CheckedAdd, CheckedSub, One, Zero};
 use std::ops::AddAssign;
-use std::{collections::BTreeMap, fmt::Debug};
 
-pub trait Config: crate::system::Config {
-    type Balance: Zero + One + AddAssign + Copy + CheckedSub + CheckedAdd;
-    // and more if needed
+pub trait Config:
+    crate::system::Config<RuntimeEvent: From<Event<Self::AccountId, Self::Balance>>>
+    + Sized
+    + AsMut<crate::system::Pallet<Self>>
+{
+    type Balance: Debug
+        + Zero
+        + One
+        + AddAssign
+        + Copy
+        + CheckedSub
+        + CheckedAdd
+        + PartialOrd
+        + Encode;
+    /// The minimum balance an account must hold to exist. An account left with less is reaped:
+    /// its remaining balance is destroyed and its `system` entries are removed.
+    const EXISTENTIAL_DEPOSIT: Self::Balance;
+    /// The reasons funds can be held for, usually an enum of all the holds of the runtime.
+    type RuntimeHoldReason: Debug + Copy + PartialEq + Encode;
+    /// The reasons funds can be frozen for, usually an enum of all the freezes of the runtime.
+    type RuntimeFreezeReason: Debug + Copy + PartialEq + Encode;
 }
 
+/// The events deposited by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId, Balance> {
+    /// `amount` was transferred from `from` to `to`.
+    Transfer {
+        from: AccountId,
+        to: AccountId,
+        amount: Balance,
+    },
+    /// `owner` allowed `delegate` to transfer up to `amount` of its funds.
+    Approved {
+        owner: AccountId,
+        delegate: AccountId,
+        amount: Balance,
+    },
+    /// `owner` cancelled the approval of `delegate`.
+    ApprovalCancelled {
+        owner: AccountId,
+        delegate: AccountId,
+    },
+    /// `delegate` transferred `amount` of the funds of `owner` to `to`.
+    TransferredApproved {
+        owner: AccountId,
+        delegate: AccountId,
+        to: AccountId,
+        amount: Balance,
+    },
+}
+
+/// A named lock on some of the free balance of an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceLock<Balance> {
+    /// The name of the lock.
+    pub id: LockIdentifier,
+    /// The amount of free balance which cannot be withdrawn.
+    pub amount: Balance,
+    /// The kinds of withdrawal prevented by the lock.
+    pub reasons: Reasons,
+}
+
+impl<Balance: Encode> Encode for BalanceLock<Balance> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.id.encode();
+        bytes.extend(self.amount.encode());
+        bytes.extend(self.reasons.encode());
+        bytes
+    }
+}
+
+/// An amount of funds held or frozen for the reason `id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdAmount<Id, Balance> {
+    /// The reason the funds are held or frozen for.
+    pub id: Id,
+    /// The amount of funds held or frozen.
+    pub amount: Balance,
+}
+
+impl<Id: Encode, Balance: Encode> Encode for IdAmount<Id, Balance> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.id.encode();
+        bytes.extend(self.amount.encode());
+        bytes
+    }
+}
+
+/// The holds of an account, with their amounts.
+pub type HoldsOf<T> = Vec<IdAmount<<T as Config>::RuntimeHoldReason, <T as Config>::Balance>>;
+
+/// The freezes of an account, with their amounts.
+pub type FreezesOf<T> = Vec<IdAmount<<T as Config>::RuntimeFreezeReason, <T as Config>::Balance>>;
+
+/// Funds held for `reason` from `who`, to be released at the end of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledRelease<AccountId, Reason, Balance> {
+    /// The account whose funds are released.
+    pub who: AccountId,
+    /// The reason the funds are held for.
+    pub reason: Reason,
+    /// The amount released, at most.
+    pub amount: Balance,
+}
+
+impl<AccountId: Encode, Reason: Encode, Balance: Encode> Encode
+    for ScheduledRelease<AccountId, Reason, Balance>
+{
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.who.encode();
+        bytes.extend(self.reason.encode());
+        bytes.extend(self.amount.encode());
+        bytes
+    }
+}
+
+/// The scheduled release type of a runtime.
+pub type ScheduledReleaseOf<T> = ScheduledRelease<
+    <T as crate::system::Config>::AccountId,
+    <T as Config>::RuntimeHoldReason,
+    <T as Config>::Balance,
+>;
+
 #[derive(Debug)]
 
 pub struct Pallet<T: Config> {
-    balances: BTreeMap<T::AccountId, T::Balance>,
+    /// The free balance of each account, which can be transferred unless it is locked.
+    balances: StorageMap<T::AccountId, T::Balance>,
+    /// The reserved balance of each account, which is owned but cannot be used.
+    reserved: StorageMap<T::AccountId, T::Balance>,
+    /// The locks on the free balance of each account. Overlapping locks do not add up: the
+    /// largest one applies.
+    locks: StorageMap<T::AccountId, Vec<BalanceLock<T::Balance>>>,
+    /// The parts of the reserved balance of each account held for a reason. Each reason holds its
+    /// own funds, which other holds and plain reserves cannot take.
+    holds: StorageMap<T::AccountId, HoldsOf<T>>,
+    /// The freezes on the free balance of each account. Like locks, overlapping freezes do not add
+    /// up, and they prevent any kind of withdrawal.
+    freezes: StorageMap<T::AccountId, FreezesOf<T>>,
+    /// The sum of the free and reserved balances of all accounts.
+    total_issuance: StorageValue<T::Balance>,
+    /// The amount each delegate is allowed to transfer out of the free balance of each owner,
+    /// keyed by `(owner, delegate)`.
+    approvals: StorageMap<(T::AccountId, T::AccountId), T::Balance>,
+    /// The held funds to release at the end of each block.
+    releases: StorageMap<u64, Vec<ScheduledReleaseOf<T>>>,
+    /// The accounts left without funds while other entries depended on them, which could not be
+    /// reaped. Balances still provides for them, and does not add a provider when they are funded
+    /// again.
+    emptied: StorageMap<T::AccountId, ()>,
 }
 
 // pub enum Call<T: Config> {
@@ -45,80 +193,1225 @@ pub struct Pallet<T: Config> {
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
+    /// Transfer `amount` of the free, unlocked balance of the `caller` to `to`.
+    /// The `caller` is reaped if it is left below the existential deposit, but `to` must end up
+    /// with at least the existential deposit.
     pub fn transfer(
         &mut self,
+        runtime: &mut T,
         caller: T::AccountId,
         to: T::AccountId,
         amount: T::Balance,
-    ) -> crate::support::DispatchResult {
-        let caller_bal = self.balance(&caller);
-        let to_bal = self.balance(&to);
+    ) -> DispatchResult {
+        self.do_transfer(runtime, &caller, &to, amount, false)
+    }
 
-        let new_caller_bal = caller_bal.checked_sub(&amount).ok_or("Not enough funds.")?;
+    /// Like `transfer`, but fails instead of reaping the `caller`.
+    pub fn transfer_keep_alive(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        self.do_transfer(runtime, &caller, &to, amount, true)
+    }
 
-        let new_to_bal = to_bal
+    /// Transfer all the free, unlocked balance of the `caller` to `to`.
+    /// With `keep_alive`, the `caller` keeps enough to stay above the existential deposit.
+    pub fn transfer_all(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        to: T::AccountId,
+        keep_alive: bool,
+    ) -> DispatchResult {
+        let mut kept = self.frozen_balance(&caller, Reasons::Transfer);
+        if keep_alive {
+            let needed = T::EXISTENTIAL_DEPOSIT
+                .checked_sub(&self.reserved_balance(&caller))
+                .unwrap_or_else(T::Balance::zero);
+            if needed > kept {
+                kept = needed;
+            }
+        }
+        let amount = self
+            .balance(&caller)
+            .checked_sub(&kept)
+            .unwrap_or_else(T::Balance::zero);
+
+        self.do_transfer(runtime, &caller, &to, amount, keep_alive)
+    }
+
+    /// Transfer `amount` from `source` to `dest`, as `transfer` would if `source` had called it.
+    /// Root only.
+    pub fn force_transfer(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        source: T::AccountId,
+        dest: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(runtime, &caller)?;
+        self.do_transfer(runtime, &source, &dest, amount, false)
+    }
+
+    /// Allow `delegate` to transfer up to `amount` of the free balance of the `caller`, replacing
+    /// any earlier approval.
+    pub fn approve_transfer(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        let key = (caller.clone(), delegate.clone());
+        if amount.is_zero() {
+            self.approvals.remove(&key);
+        } else {
+            self.approvals.insert(key, amount);
+        }
+
+        deposit_event(
+            runtime,
+            Event::Approved {
+                owner: caller,
+                delegate,
+                amount,
+            },
+        );
+        Ok(())
+    }
+
+    /// Cancel the approval given by the `caller` to `delegate`.
+    pub fn cancel_approval(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+    ) -> DispatchResult {
+        let key = (caller.clone(), delegate.clone());
+        if !self.approvals.contains_key(&key) {
+            return Err("No approval to cancel.");
+        }
+        self.approvals.remove(&key);
+
+        deposit_event(
+            runtime,
+            Event::ApprovalCancelled {
+                owner: caller,
+                delegate,
+            },
+        );
+        Ok(())
+    }
+
+    /// Transfer `amount` of the free balance of `owner` to `to`, spending the approval given by
+    /// `owner` to the `caller`. `owner` is reaped if it is left below the existential deposit.
+    /// Fails if `to` is `owner`, as no funds would move.
+    pub fn transfer_approved(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        owner: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        if owner == to {
+            return Err("Cannot transfer approved funds back to their owner.");
+        }
+        let key = (owner.clone(), caller.clone());
+        let remaining = self
+            .approval(&owner, &caller)
+            .checked_sub(&amount)
+            .ok_or("Insufficient allowance.")?;
+
+        self.do_transfer(runtime, &owner, &to, amount, false)?;
+        if remaining.is_zero() {
+            self.approvals.remove(&key);
+        } else {
+            self.approvals.insert(key, remaining);
+        }
+
+        deposit_event(
+            runtime,
+            Event::TransferredApproved {
+                owner,
+                delegate: caller,
+                to,
+                amount,
+            },
+        );
+        Ok(())
+    }
+
+    /// Create `amount` new funds in the account `to`. Root only.
+    pub fn mint(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(runtime, &caller)?;
+        let new_bal = self
+            .balance(&to)
             .checked_add(&amount)
             .ok_or("over/underflow occured.")?;
 
-        self.set_balance(&caller, new_caller_bal);
-        self.set_balance(&to, new_to_bal);
+        if self.total_with_reserved(&to, new_bal) < T::EXISTENTIAL_DEPOSIT {
+            return Err("Recipient would be left below the existential deposit.");
+        }
 
-        Ok(())
+        self.provide(runtime, &to);
+        self.set_balance(&to, new_bal)
+    }
+
+    /// Destroy `amount` funds from the account `who`, reaping it if it is left below the existential
+    /// deposit. Root only.
+    pub fn burn(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(runtime, &caller)?;
+        let new_bal = self
+            .balance(&who)
+            .checked_sub(&amount)
+            .ok_or("Not enough funds.")?;
+
+        self.settle_balance(runtime, &who, new_bal)
+    }
+
+    /// Set the balance of `who` to `amount`, creating or destroying funds as needed, and reaping
+    /// the account if it is left below the existential deposit. Root only.
+    pub fn force_set_balance(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(runtime, &caller)?;
+        self.settle_balance(runtime, &who, amount)
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+    /// Release the held funds scheduled to be released at the end of this block.
+    fn on_finalize(&mut self, runtime: &mut T) {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        let now = system.block_number().into();
+        let Some(releases) = self.releases.get(&now).cloned() else {
+            return;
+        };
+        self.releases.remove(&now);
+        for ScheduledRelease {
+            who,
+            reason,
+            amount,
+        } in releases
+        {
+            self.with_references(runtime, &[&who], |pallet| {
+                pallet.release(reason, &who, amount)
+            });
+        }
+    }
+}
+
+impl<T: Config> Prune for Pallet<T> {
+    fn prune(&mut self, earliest: u64) {
+        self.balances.prune(earliest);
+        self.reserved.prune(earliest);
+        self.locks.prune(earliest);
+        self.holds.prune(earliest);
+        self.freezes.prune(earliest);
+        self.total_issuance.prune(earliest);
+        self.approvals.prune(earliest);
+        self.releases.prune(earliest);
+        self.emptied.prune(earliest);
     }
 }
 
 impl<T: Config> Pallet<T> {
     pub fn new() -> Self {
         Self {
-            balances: BTreeMap::<T::AccountId, T::Balance>::new(),
+            balances: StorageMap::new("balances", "balances"),
+            reserved: StorageMap::new("balances", "reserved"),
+            locks: StorageMap::new("balances", "locks"),
+            holds: StorageMap::new("balances", "holds"),
+            freezes: StorageMap::new("balances", "freezes"),
+            total_issuance: StorageValue::new("balances", "total_issuance"),
+            approvals: StorageMap::new("balances", "approvals"),
+            releases: StorageMap::new("balances", "releases"),
+            emptied: StorageMap::new("balances", "emptied"),
+        }
+    }
+
+    /// Transfer `amount` of the free, unlocked balance of `from` to `to`.
+    /// `from` is reaped if it is left below the existential deposit, unless `keep_alive` is set, in
+    /// which case the transfer fails.
+    fn do_transfer(
+        &mut self,
+        runtime: &mut T,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance,
+        keep_alive: bool,
+    ) -> DispatchResult {
+        let from_bal = self.balance(from);
+        let to_bal = self.balance(to);
+
+        let new_from_bal = from_bal.checked_sub(&amount).ok_or("Not enough funds.")?;
+
+        if new_from_bal < self.frozen_balance(from, Reasons::Transfer) {
+            return Err("Balance is locked.");
+        }
+
+        // Funds sent to oneself stay where they are. Going on would credit the account with
+        // `amount` out of thin air, as both balances were read before writing either.
+        if from == to {
+            return Ok(());
+        }
+
+        if keep_alive && self.total_with_reserved(from, new_from_bal) < T::EXISTENTIAL_DEPOSIT {
+            return Err("Transfer would kill the sender account.");
+        }
+
+        let new_to_bal = to_bal
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+
+        if self.total_with_reserved(to, new_to_bal) < T::EXISTENTIAL_DEPOSIT {
+            return Err("Recipient would be left below the existential deposit.");
+        }
+
+        self.settle_balance(runtime, from, new_from_bal)?;
+        self.provide(runtime, to);
+        self.set_balance(to, new_to_bal)?;
+
+        deposit_event(
+            runtime,
+            Event::Transfer {
+                from: from.clone(),
+                to: to.clone(),
+                amount,
+            },
+        );
+        Ok(())
+    }
+
+    /// Set the free balance of `who`, without checking the existential deposit.
+    /// The total issuance is updated with the funds created or destroyed.
+    fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let old = self.balance(who);
+        self.update_issuance(old, amount)?;
+        write_balance(&mut self.balances, who, amount);
+        Ok(())
+    }
+
+    /// Set the reserved balance of `who`, without checking the existential deposit.
+    /// The total issuance is updated with the funds created or destroyed.
+    fn set_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let old = self.reserved_balance(who);
+        self.update_issuance(old, amount)?;
+        write_balance(&mut self.reserved, who, amount);
+        Ok(())
+    }
+
+    /// Update the total issuance for a balance going from `old` to `new`.
+    fn update_issuance(&mut self, old: T::Balance, new: T::Balance) -> DispatchResult {
+        let total_issuance = self
+            .total_issuance()
+            .checked_sub(&old)
+            .and_then(|issuance| issuance.checked_add(&new))
+            .ok_or("over/underflow occured.")?;
+        self.total_issuance.put(total_issuance);
+        Ok(())
+    }
+
+    /// Set the free balance of `who`, reaping the account if its free and reserved balance are
+    /// left below the existential deposit.
+    fn settle_balance(
+        &mut self,
+        runtime: &mut T,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        if self.total_with_reserved(who, amount) < T::EXISTENTIAL_DEPOSIT {
+            self.reap_account(runtime, who)
+        } else {
+            self.provide(runtime, who);
+            self.set_balance(who, amount)
+        }
+    }
+
+    /// Remove the balances, locks, holds and freezes of `who`, destroying any dust left, and drop
+    /// the provider of its `system` account, which is reaped unless something else keeps it alive.
+    /// Fails if other entries still depend on the account.
+    fn reap_account(&mut self, runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        system.dec_providers(who)?;
+        self.set_balance(who, T::Balance::zero())?;
+        self.set_reserved(who, T::Balance::zero())?;
+        self.locks.remove(who);
+        self.holds.remove(who);
+        self.freezes.remove(who);
+        Ok(())
+    }
+
+    /// Make balances a provider of the `system` account of `who`, if it has no funds yet and is
+    /// about to be given some. Balances provides for every account with funds, until it is reaped.
+    fn provide(&mut self, runtime: &mut T, who: &T::AccountId) {
+        if !self.has_funds(who) {
+            self.add_provider(runtime.as_mut(), who);
+        }
+    }
+
+    /// Add the provider of balances to the `system` account of `who`, which was just given funds,
+    /// unless it still has the one it had when it was emptied.
+    fn add_provider(&mut self, system: &mut crate::system::Pallet<T>, who: &T::AccountId) {
+        if self.emptied.contains_key(who) {
+            self.emptied.remove(who);
+        } else {
+            system.inc_providers(who);
+        }
+    }
+
+    /// Run `f` on this pallet, then update the references balances has on the `system` accounts
+    /// of `accounts`: it provides for an account once it has funds, and consumes it while some of
+    /// them are reserved, so that it cannot be reaped. An account left without funds is reaped as
+    /// `reap_account` does, or recorded as emptied if other entries still depend on it.
+    fn with_references<R>(
+        &mut self,
+        runtime: &mut T,
+        accounts: &[&T::AccountId],
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let mut accounts = accounts.to_vec();
+        accounts.dedup();
+        let before: Vec<(bool, bool)> = accounts
+            .iter()
+            .map(|who| (self.has_funds(who), self.reserved.contains_key(who)))
+            .collect();
+        let result = f(self);
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        for (who, (had_funds, had_reserved)) in accounts.into_iter().zip(before) {
+            match (had_reserved, self.reserved.contains_key(who)) {
+                (false, true) => system.inc_consumers(who),
+                (true, false) => system.dec_consumers(who),
+                _ => {}
+            }
+            match (had_funds, self.has_funds(who)) {
+                (false, true) => self.add_provider(system, who),
+                (true, false) => match system.dec_providers(who) {
+                    Ok(()) => {
+                        self.locks.remove(who);
+                        self.holds.remove(who);
+                        self.freezes.remove(who);
+                    }
+                    // The account keeps the provider of balances while other entries depend on it.
+                    Err(_) => self.emptied.insert(who.clone(), ()),
+                },
+                _ => {}
+            }
         }
+        result
+    }
+
+    /// Whether `who` has any free or reserved funds.
+    fn has_funds(&self, who: &T::AccountId) -> bool {
+        self.balances.contains_key(who) || self.reserved.contains_key(who)
+    }
+
+    /// The total amount of funds held by all accounts.
+    pub fn total_issuance(&self) -> T::Balance {
+        *self.total_issuance.get().unwrap_or(&T::Balance::zero())
+    }
+
+    /// Check that the free and reserved balances of all accounts add up to the total issuance.
+    pub fn check_invariants(&self) -> DispatchResult {
+        let mut total = T::Balance::zero();
+        let free = self.balances.iter_from(None);
+        for (_, balance) in free.chain(self.reserved.iter_from(None)) {
+            total = total
+                .checked_add(balance)
+                .ok_or("sum of balances overflows")?;
+        }
+
+        if total != self.total_issuance() {
+            return Err("sum of balances does not match the total issuance");
+        }
+
+        for (who, holds) in self.holds.iter_from(None) {
+            let mut held = T::Balance::zero();
+            for hold in holds {
+                held = held
+                    .checked_add(&hold.amount)
+                    .ok_or("sum of holds overflows")?;
+            }
+            if held > self.reserved_balance(who) {
+                return Err("held balance exceeds the reserved balance");
+            }
+        }
+        Ok(())
     }
 
-    pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+    /// The amount `delegate` is allowed to transfer out of the free balance of `owner`.
+    pub fn approval(&self, owner: &T::AccountId, delegate: &T::AccountId) -> T::Balance {
+        *self
+            .approvals
+            .get(&(owner.clone(), delegate.clone()))
+            .unwrap_or(&T::Balance::zero())
     }
 
+    /// The free balance of `who`.
     pub fn balance(&self, who: &T::AccountId) -> T::Balance {
         *self.balances.get(who).unwrap_or(&T::Balance::zero())
     }
+
+    /// The reserved balance of `who`.
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        *self.reserved.get(who).unwrap_or(&T::Balance::zero())
+    }
+
+    /// The locks on the free balance of `who`.
+    pub fn locks(&self, who: &T::AccountId) -> Vec<BalanceLock<T::Balance>> {
+        self.locks.get(who).cloned().unwrap_or_default()
+    }
+
+    /// The part of the free balance of `who` which cannot be withdrawn for `reason`, because of
+    /// its locks and freezes.
+    pub fn frozen_balance(&self, who: &T::AccountId, reason: Reasons) -> T::Balance {
+        let mut frozen = T::Balance::zero();
+        for lock in self.locks.get(who).into_iter().flatten() {
+            if lock.reasons.includes(reason) && lock.amount > frozen {
+                frozen = lock.amount;
+            }
+        }
+        for freeze in self.freezes.get(who).into_iter().flatten() {
+            if freeze.amount > frozen {
+                frozen = freeze.amount;
+            }
+        }
+        frozen
+    }
+
+    /// The holds on the reserved balance of `who`.
+    pub fn holds(&self, who: &T::AccountId) -> HoldsOf<T> {
+        self.holds.get(who).cloned().unwrap_or_default()
+    }
+
+    /// The part of the reserved balance of `who` held for `reason`.
+    pub fn balance_on_hold(&self, reason: T::RuntimeHoldReason, who: &T::AccountId) -> T::Balance {
+        self.holds
+            .get(who)
+            .into_iter()
+            .flatten()
+            .find(|hold| hold.id == reason)
+            .map_or_else(T::Balance::zero, |hold| hold.amount)
+    }
+
+    /// The part of the reserved balance of `who` held for any reason.
+    pub fn total_balance_on_hold(&self, who: &T::AccountId) -> T::Balance {
+        let mut held = T::Balance::zero();
+        for hold in self.holds.get(who).into_iter().flatten() {
+            // This cannot overflow, as it is at most the reserved balance.
+            held += hold.amount;
+        }
+        held
+    }
+
+    /// The freezes on the free balance of `who`.
+    pub fn freezes(&self, who: &T::AccountId) -> FreezesOf<T> {
+        self.freezes.get(who).cloned().unwrap_or_default()
+    }
+
+    /// The part of the free balance of `who` frozen for `id`.
+    pub fn balance_frozen(&self, id: T::RuntimeFreezeReason, who: &T::AccountId) -> T::Balance {
+        self.freezes
+            .get(who)
+            .into_iter()
+            .flatten()
+            .find(|freeze| freeze.id == id)
+            .map_or_else(T::Balance::zero, |freeze| freeze.amount)
+    }
+
+    /// The part of the reserved balance of `who` which is not held for any reason.
+    fn unheld_reserved(&self, who: &T::AccountId) -> T::Balance {
+        // This cannot underflow, as the holds are part of the reserved balance.
+        self.reserved_balance(who) - self.total_balance_on_hold(who)
+    }
+
+    /// The free balance `who` would have with `free`, plus its reserved balance.
+    fn total_with_reserved(&self, who: &T::AccountId, free: T::Balance) -> T::Balance {
+        // This cannot overflow, as it is at most the total issuance.
+        free + self.reserved_balance(who)
+    }
+
+    /// Get up to `limit` account balances, in account order, starting after the account `cursor`.
+    pub fn balances(
+        &self,
+        cursor: Option<&T::AccountId>,
+        limit: usize,
+    ) -> Page<T::AccountId, T::Balance> {
+        self.balances.page(cursor, limit)
+    }
+}
+
+// Reserves, locks, holds and freezes are meant to be used by other pallets, through the
+// `Currency` traits.
+impl<T: Config> Pallet<T> {
+    /// Move `amount` from the free balance of `who` to its reserved balance.
+    /// Funds under a lock preventing reserves cannot be reserved.
+    pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_free = self
+            .balance(who)
+            .checked_sub(&amount)
+            .ok_or("Not enough funds.")?;
+
+        if new_free < self.frozen_balance(who, Reasons::Reserve) {
+            return Err("Balance is locked.");
+        }
+
+        let new_reserved = self
+            .reserved_balance(who)
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+
+        self.set_balance(who, new_free)?;
+        self.set_reserved(who, new_reserved)
+    }
+
+    /// Move up to `amount` from the reserved balance of `who` back to its free balance.
+    /// Returns the part of `amount` which was not reserved, and so could not be unreserved. Funds
+    /// on hold are not unreserved.
+    pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let (actual, remaining) = split(self.unheld_reserved(who), amount);
+        // Neither can overflow nor underflow, as `actual` is at most the reserved balance.
+        let _ = self.set_reserved(who, self.reserved_balance(who) - actual);
+        let _ = self.set_balance(who, self.balance(who) + actual);
+        remaining
+    }
+
+    /// Destroy up to `amount` from the reserved balance of `who`.
+    /// Returns the part of `amount` which was not reserved, and so could not be slashed. Funds on
+    /// hold are not slashed. The account is not reaped, even if it is left below the existential
+    /// deposit.
+    pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let (actual, remaining) = split(self.unheld_reserved(who), amount);
+        // This cannot underflow, as `actual` is at most the reserved balance.
+        let _ = self.set_reserved(who, self.reserved_balance(who) - actual);
+        remaining
+    }
+
+    /// Move up to `amount` from the reserved balance of `slashed` to the free or reserved balance
+    /// of `beneficiary`, as `status` says.
+    /// Returns the part of `amount` which was not reserved, and so could not be moved. Funds on
+    /// hold are not moved.
+    pub fn repatriate_reserved(
+        &mut self,
+        slashed: &T::AccountId,
+        beneficiary: &T::AccountId,
+        amount: T::Balance,
+        status: BalanceStatus,
+    ) -> Result<T::Balance, &'static str> {
+        let (actual, remaining) = split(self.unheld_reserved(slashed), amount);
+
+        if slashed == beneficiary {
+            return Ok(match status {
+                BalanceStatus::Free => self.unreserve(slashed, amount),
+                BalanceStatus::Reserved => remaining,
+            });
+        }
+
+        self.ensure_can_receive(beneficiary, actual)?;
+        // This cannot underflow, as `actual` is at most the reserved balance.
+        self.set_reserved(slashed, self.reserved_balance(slashed) - actual)?;
+        match status {
+            BalanceStatus::Free => {
+                let new_free = self.balance(beneficiary) + actual;
+                self.set_balance(beneficiary, new_free)?;
+            }
+            BalanceStatus::Reserved => {
+                let new_reserved = self.reserved_balance(beneficiary) + actual;
+                self.set_reserved(beneficiary, new_reserved)?;
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Lock `amount` of the free balance of `who` for `reasons`, under the name `id`.
+    /// This replaces any lock of the same name. Locks do not require the funds to be there.
+    pub fn set_lock(
+        &mut self,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: Reasons,
+    ) {
+        let mut locks = self.locks(who);
+        locks.retain(|lock| lock.id != id);
+        if !amount.is_zero() {
+            locks.push(BalanceLock {
+                id,
+                amount,
+                reasons,
+            });
+        }
+        self.write_locks(who, locks);
+    }
+
+    /// Extend the lock named `id` on the free balance of `who`, so it locks at least `amount` for
+    /// both its reasons and `reasons`. Creates the lock if it does not exist.
+    pub fn extend_lock(
+        &mut self,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: Reasons,
+    ) {
+        let (amount, reasons) = match self.locks(who).into_iter().find(|lock| lock.id == id) {
+            Some(lock) => (
+                if lock.amount > amount {
+                    lock.amount
+                } else {
+                    amount
+                },
+                if lock.reasons == reasons {
+                    reasons
+                } else {
+                    Reasons::All
+                },
+            ),
+            None => (amount, reasons),
+        };
+        self.set_lock(id, who, amount, reasons);
+    }
+
+    /// Remove the lock named `id` from the free balance of `who`.
+    pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        let mut locks = self.locks(who);
+        locks.retain(|lock| lock.id != id);
+        self.write_locks(who, locks);
+    }
+
+    fn write_locks(&mut self, who: &T::AccountId, locks: Vec<BalanceLock<T::Balance>>) {
+        if locks.is_empty() {
+            self.locks.remove(who);
+        } else {
+            self.locks.insert(who.clone(), locks);
+        }
+    }
+
+    /// Move `amount` from the free balance of `who` to its reserved balance, held for `reason`.
+    /// Funds under a lock preventing reserves cannot be held.
+    pub fn hold(
+        &mut self,
+        reason: T::RuntimeHoldReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        self.reserve(who, amount)?;
+        // This cannot overflow, as it is at most the reserved balance.
+        let held = self.balance_on_hold(reason, who) + amount;
+        write_id_amount(&mut self.holds, who, reason, held);
+        Ok(())
+    }
+
+    /// Move up to `amount` from the balance of `who` held for `reason` back to its free balance.
+    /// Returns the part of `amount` which was not held, and so could not be released.
+    pub fn release(
+        &mut self,
+        reason: T::RuntimeHoldReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> T::Balance {
+        let held = self.balance_on_hold(reason, who);
+        let (actual, remaining) = split(held, amount);
+        write_id_amount(&mut self.holds, who, reason, held - actual);
+        // Neither can overflow nor underflow, as `actual` is at most the reserved balance.
+        let _ = self.set_reserved(who, self.reserved_balance(who) - actual);
+        let _ = self.set_balance(who, self.balance(who) + actual);
+        remaining
+    }
+
+    /// Release up to `amount` from the balance of `who` held for `reason` at the end of the block
+    /// `at`, which must be in the future. `amount` must be held when the release is scheduled,
+    /// but whatever is left of it then is released.
+    pub fn release_at(
+        &mut self,
+        runtime: &mut T,
+        reason: T::RuntimeHoldReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+        at: T::BlockNumber,
+    ) -> DispatchResult {
+        let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+        if at.into() <= system.block_number().into() {
+            return Err("the release block must be in the future");
+        }
+        if amount > self.balance_on_hold(reason, who) {
+            return Err("Not enough funds on hold.");
+        }
+        let mut releases = self.scheduled_releases(at);
+        releases.push(ScheduledRelease {
+            who: who.clone(),
+            reason,
+            amount,
+        });
+        self.releases.insert(at.into(), releases);
+        Ok(())
+    }
+
+    /// The held funds to release at the end of the block `at`.
+    pub fn scheduled_releases(&self, at: T::BlockNumber) -> Vec<ScheduledReleaseOf<T>> {
+        self.releases.get(&at.into()).cloned().unwrap_or_default()
+    }
+
+    /// Destroy up to `amount` from the balance of `who` held for `reason`.
+    /// Returns the part of `amount` which was not held, and so could not be burned. The account is
+    /// not reaped, even if it is left below the existential deposit.
+    pub fn burn_held(
+        &mut self,
+        reason: T::RuntimeHoldReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> T::Balance {
+        let held = self.balance_on_hold(reason, who);
+        let (actual, remaining) = split(held, amount);
+        write_id_amount(&mut self.holds, who, reason, held - actual);
+        // This cannot underflow, as `actual` is at most the reserved balance.
+        let _ = self.set_reserved(who, self.reserved_balance(who) - actual);
+        remaining
+    }
+
+    /// Move up to `amount` from the balance of `source` held for `reason` to `dest`, either to its
+    /// free balance or held for the same `reason`, as `status` says.
+    /// Returns the part of `amount` which was not held, and so could not be moved.
+    pub fn transfer_on_hold(
+        &mut self,
+        reason: T::RuntimeHoldReason,
+        source: &T::AccountId,
+        dest: &T::AccountId,
+        amount: T::Balance,
+        status: BalanceStatus,
+    ) -> Result<T::Balance, &'static str> {
+        let held = self.balance_on_hold(reason, source);
+        let (actual, remaining) = split(held, amount);
+
+        if source == dest {
+            return Ok(match status {
+                BalanceStatus::Free => self.release(reason, source, amount),
+                BalanceStatus::Reserved => remaining,
+            });
+        }
+
+        self.ensure_can_receive(dest, actual)?;
+        write_id_amount(&mut self.holds, source, reason, held - actual);
+        // This cannot underflow, as `actual` is at most the reserved balance.
+        self.set_reserved(source, self.reserved_balance(source) - actual)?;
+        match status {
+            BalanceStatus::Free => {
+                let new_free = self.balance(dest) + actual;
+                self.set_balance(dest, new_free)?;
+            }
+            BalanceStatus::Reserved => {
+                let new_reserved = self.reserved_balance(dest) + actual;
+                self.set_reserved(dest, new_reserved)?;
+                let held = self.balance_on_hold(reason, dest) + actual;
+                write_id_amount(&mut self.holds, dest, reason, held);
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Freeze `amount` of the free balance of `who` for `id`, replacing any freeze for `id`.
+    /// Freezes do not require the funds to be there.
+    pub fn set_freeze(
+        &mut self,
+        id: T::RuntimeFreezeReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) {
+        write_id_amount(&mut self.freezes, who, id, amount);
+    }
+
+    /// Extend the freeze for `id` on the free balance of `who`, so it freezes at least `amount`.
+    /// Creates the freeze if it does not exist.
+    pub fn extend_freeze(
+        &mut self,
+        id: T::RuntimeFreezeReason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) {
+        if amount > self.balance_frozen(id, who) {
+            self.set_freeze(id, who, amount);
+        }
+    }
+
+    /// Remove the freeze for `id` from the free balance of `who`.
+    pub fn thaw(&mut self, id: T::RuntimeFreezeReason, who: &T::AccountId) {
+        self.set_freeze(id, who, T::Balance::zero());
+    }
+
+    /// Check that `who` can be given `amount`, as it would then hold at least the existential
+    /// deposit.
+    fn ensure_can_receive(&self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let total = self
+            .total_with_reserved(who, self.balance(who))
+            .checked_add(&amount)
+            .ok_or("over/underflow occured.")?;
+        if total < T::EXISTENTIAL_DEPOSIT {
+            return Err("Recipient would be left below the existential deposit.");
+        }
+        Ok(())
+    }
+}
+
+/// Run `f` on the balances pallet of `runtime`, detached from it so `f` can also access the rest
+/// of the runtime.
+fn with_pallet<T: Config + AsMut<Pallet<T>>, R>(
+    runtime: &mut T,
+    f: impl FnOnce(&mut Pallet<T>, &mut T) -> R,
+) -> R {
+    let mut pallet = core::mem::replace(AsMut::<Pallet<T>>::as_mut(runtime), Pallet::new());
+    let result = f(&mut pallet, runtime);
+    *AsMut::<Pallet<T>>::as_mut(runtime) = pallet;
+    result
+}
+
+/// Run `f` on the balances pallet of `runtime`, updating the references balances has on the
+/// `system` accounts of `accounts` as `Pallet::with_references` does.
+fn with_references<T: Config + AsMut<Pallet<T>>, R>(
+    runtime: &mut T,
+    accounts: &[&T::AccountId],
+    f: impl FnOnce(&mut Pallet<T>) -> R,
+) -> R {
+    with_pallet(runtime, |pallet, runtime| {
+        pallet.with_references(runtime, accounts, f)
+    })
+}
+
+impl<T: Config + AsRef<Pallet<T>> + AsMut<Pallet<T>>> Currency<T> for Pallet<T> {
+    type AccountId = T::AccountId;
+    type Balance = T::Balance;
+
+    fn total_issuance(runtime: &T) -> T::Balance {
+        runtime.as_ref().total_issuance()
+    }
+
+    fn free_balance(runtime: &T, who: &T::AccountId) -> T::Balance {
+        runtime.as_ref().balance(who)
+    }
+
+    fn transfer(
+        runtime: &mut T,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance,
+        keep_alive: bool,
+    ) -> DispatchResult {
+        with_pallet(runtime, |pallet, runtime| {
+            pallet.do_transfer(runtime, from, to, amount, keep_alive)
+        })
+    }
+}
+
+impl<T: Config + AsRef<Pallet<T>> + AsMut<Pallet<T>>> ReservableCurrency<T> for Pallet<T> {
+    fn reserved_balance(runtime: &T, who: &T::AccountId) -> T::Balance {
+        runtime.as_ref().reserved_balance(who)
+    }
+
+    fn reserve(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        with_references(runtime, &[who], |pallet| pallet.reserve(who, amount))
+    }
+
+    fn unreserve(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        with_references(runtime, &[who], |pallet| pallet.unreserve(who, amount))
+    }
+
+    fn slash_reserved(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        with_references(runtime, &[who], |pallet| pallet.slash_reserved(who, amount))
+    }
+
+    fn repatriate_reserved(
+        runtime: &mut T,
+        slashed: &T::AccountId,
+        beneficiary: &T::AccountId,
+        amount: T::Balance,
+        status: BalanceStatus,
+    ) -> Result<T::Balance, &'static str> {
+        with_references(runtime, &[slashed, beneficiary], |pallet| {
+            pallet.repatriate_reserved(slashed, beneficiary, amount, status)
+        })
+    }
+}
+
+impl<T: Config + AsRef<Pallet<T>> + AsMut<Pallet<T>>> LockableCurrency<T> for Pallet<T> {
+    fn set_lock(
+        runtime: &mut T,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: Reasons,
+    ) {
+        AsMut::<Pallet<T>>::as_mut(runtime).set_lock(id, who, amount, reasons)
+    }
+
+    fn extend_lock(
+        runtime: &mut T,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: Reasons,
+    ) {
+        AsMut::<Pallet<T>>::as_mut(runtime).extend_lock(id, who, amount, reasons)
+    }
+
+    fn remove_lock(runtime: &mut T, id: LockIdentifier, who: &T::AccountId) {
+        AsMut::<Pallet<T>>::as_mut(runtime).remove_lock(id, who)
+    }
+}
+
+impl<T: Config + AsRef<Pallet<T>> + AsMut<Pallet<T>>> HoldableCurrency<T> for Pallet<T> {
+    type Reason = T::RuntimeHoldReason;
+    type BlockNumber = T::BlockNumber;
+
+    fn balance_on_hold(runtime: &T, reason: Self::Reason, who: &T::AccountId) -> T::Balance {
+        runtime.as_ref().balance_on_hold(reason, who)
+    }
+
+    fn hold(
+        runtime: &mut T,
+        reason: Self::Reason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        with_references(runtime, &[who], |pallet| pallet.hold(reason, who, amount))
+    }
+
+    fn release(
+        runtime: &mut T,
+        reason: Self::Reason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> T::Balance {
+        with_references(runtime, &[who], |pallet| {
+            pallet.release(reason, who, amount)
+        })
+    }
+
+    fn release_at(
+        runtime: &mut T,
+        reason: Self::Reason,
+        who: &T::AccountId,
+        amount: T::Balance,
+        at: T::BlockNumber,
+    ) -> DispatchResult {
+        with_pallet(runtime, |pallet, runtime| {
+            pallet.release_at(runtime, reason, who, amount, at)
+        })
+    }
+
+    fn burn_held(
+        runtime: &mut T,
+        reason: Self::Reason,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> T::Balance {
+        with_references(runtime, &[who], |pallet| {
+            pallet.burn_held(reason, who, amount)
+        })
+    }
+
+    fn transfer_on_hold(
+        runtime: &mut T,
+        reason: Self::Reason,
+        source: &T::AccountId,
+        dest: &T::AccountId,
+        amount: T::Balance,
+        status: BalanceStatus,
+    ) -> Result<T::Balance, &'static str> {
+        with_references(runtime, &[source, dest], |pallet| {
+            pallet.transfer_on_hold(reason, source, dest, amount, status)
+        })
+    }
+}
+
+impl<T: Config + AsRef<Pallet<T>> + AsMut<Pallet<T>>> FreezableCurrency<T> for Pallet<T> {
+    type Id = T::RuntimeFreezeReason;
+
+    fn balance_frozen(runtime: &T, id: Self::Id, who: &T::AccountId) -> T::Balance {
+        runtime.as_ref().balance_frozen(id, who)
+    }
+
+    fn set_freeze(runtime: &mut T, id: Self::Id, who: &T::AccountId, amount: T::Balance) {
+        AsMut::<Pallet<T>>::as_mut(runtime).set_freeze(id, who, amount)
+    }
+
+    fn extend_freeze(runtime: &mut T, id: Self::Id, who: &T::AccountId, amount: T::Balance) {
+        AsMut::<Pallet<T>>::as_mut(runtime).extend_freeze(id, who, amount)
+    }
+
+    fn thaw(runtime: &mut T, id: Self::Id, who: &T::AccountId) {
+        AsMut::<Pallet<T>>::as_mut(runtime).thaw(id, who)
+    }
+}
+
+/// Write a balance to `map`, removing the entry of `who` instead of storing zero.
+fn write_balance<AccountId: Ord + Clone + Encode, Balance: Zero + Encode>(
+    map: &mut StorageMap<AccountId, Balance>,
+    who: &AccountId,
+    amount: Balance,
+) {
+    if amount.is_zero() {
+        map.remove(who);
+    } else {
+        map.insert(who.clone(), amount);
+    }
+}
+
+/// Set the amount for `id` in the entry of `who` in `map`, dropping it instead of storing zero, and
+/// removing the entry once it is empty.
+fn write_id_amount<AccountId, Id, Balance>(
+    map: &mut StorageMap<AccountId, Vec<IdAmount<Id, Balance>>>,
+    who: &AccountId,
+    id: Id,
+    amount: Balance,
+) where
+    AccountId: Ord + Clone + Encode,
+    Id: PartialEq + Clone + Encode,
+    Balance: Zero + Clone + Encode,
+{
+    let mut entries = map.get(who).cloned().unwrap_or_default();
+    entries.retain(|entry| entry.id != id);
+    if !amount.is_zero() {
+        entries.push(IdAmount { id, amount });
+    }
+    if entries.is_empty() {
+        map.remove(who);
+    } else {
+        map.insert(who.clone(), entries);
+    }
+}
+
+/// Split `amount` into the part which can be taken from `available`, and the remaining part.
+fn split<Balance: Zero + CheckedSub + PartialOrd + Copy>(
+    available: Balance,
+    amount: Balance,
+) -> (Balance, Balance) {
+    if amount > available {
+        (
+            available,
+            amount.checked_sub(&available).unwrap_or_else(Zero::zero),
+        )
+    } else {
+        (amount, Zero::zero())
+    }
+}
+
+/// Deposit an event of this pallet in the `system` pallet of the `runtime`.
+fn deposit_event<T: Config>(runtime: &mut T, event: Event<T::AccountId, T::Balance>) {
+    let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+    system.deposit_event(event);
+}
+
+/// Check that `who` is allowed to make root-only calls, according to the `system` pallet.
+fn ensure_root<T: Config>(runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+    let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+    system.ensure_root(who)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    struct testConfig;
-    impl crate::system::Config for testConfig {
+    struct TestRuntime {
+        system: crate::system::Pallet<TestRuntime>,
+        balances: Pallet<TestRuntime>,
+    }
+
+    impl TestRuntime {
+        fn new() -> Self {
+            Self {
+                system: crate::system::Pallet::new(),
+                balances: Pallet::new(),
+            }
+        }
+    }
+
+    impl AsMut<crate::system::Pallet<TestRuntime>> for TestRuntime {
+        fn as_mut(&mut self) -> &mut crate::system::Pallet<TestRuntime> {
+            &mut self.system
+        }
+    }
+
+    impl AsRef<Pallet<TestRuntime>> for TestRuntime {
+        fn as_ref(&self) -> &Pallet<TestRuntime> {
+            &self.balances
+        }
+    }
+
+    impl AsMut<Pallet<TestRuntime>> for TestRuntime {
+        fn as_mut(&mut self) -> &mut Pallet<TestRuntime> {
+            &mut self.balances
+        }
+    }
+
+    impl crate::system::Config for TestRuntime {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = Event<String, u32>;
+        const PRUNING: crate::support::storage::Pruning = crate::support::storage::Pruning::Archive;
+        const BLOCK_HASH_COUNT: u64 = 256;
     }
-    impl Config for testConfig {
+    impl Config for TestRuntime {
         type Balance = u32;
+        const EXISTENTIAL_DEPOSIT: u32 = 10;
+        type RuntimeHoldReason = TestReason;
+        type RuntimeFreezeReason = TestReason;
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TestReason {
+        Deposit,
+        Staking,
+    }
+
+    impl Encode for TestReason {
+        fn encode(&self) -> Vec<u8> {
+            vec![*self as u8]
+        }
     }
 
     #[test]
     fn init_balances() {
-        let mut balances = Pallet::<testConfig>::new();
+        let mut balances = Pallet::<TestRuntime>::new();
         assert_eq!(balances.balance(&"alice".to_string()), 0);
-        balances.set_balance(&"alice".to_string(), 100);
+        balances.set_balance(&"alice".to_string(), 100).unwrap();
         assert_eq!(balances.balance(&"alice".to_string()), 100);
         assert_eq!(balances.balance(&"bob".to_string()), 0);
     }
 
     #[test]
     fn transfer_balance() {
-        let mut transfer = Pallet::<testConfig>::new();
+        let mut transfer = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
 
         // - That `alice` cannot transfer funds she does not have.
-        let result = transfer.transfer(&"Alice".to_string(), &"Bob".to_string(), 100);
+        let result = transfer.transfer(&mut runtime, "Alice".to_string(), "Bob".to_string(), 100);
         assert_eq!(result, Err("Not enough funds."));
 
         // set Alice balance to 150
-        transfer.set_balance(&"Alice".to_string(), 150);
+        transfer.set_balance(&"Alice".to_string(), 150).unwrap();
 
         // - That `alice` can successfully transfer funds to `bob`.
-        let result = transfer.transfer(&"Alice".to_string(), &"Bob".to_string(), 100);
+        let result = transfer.transfer(&mut runtime, "Alice".to_string(), "Bob".to_string(), 100);
         assert_eq!(result, Ok(()));
 
         // - That the balance of `alice` and `bob` is correctly updated.
@@ -128,4 +1421,691 @@ mod test {
         let bob_new_bal = transfer.balance(&"Bob".to_string());
         assert_eq!(bob_new_bal, 100);
     }
+
+    #[test]
+    fn transfer_to_self() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        let result = balances.transfer(&mut runtime, alice.clone(), alice.clone(), 101);
+        assert_eq!(result, Err("Not enough funds."));
+        let result = balances.transfer(&mut runtime, alice.clone(), alice.clone(), 60);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+        // Nothing moves, so sending the whole balance cannot kill the account either.
+        let result = balances.transfer_keep_alive(&mut runtime, alice.clone(), alice.clone(), 100);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.total_issuance(), 100);
+    }
+
+    #[test]
+    fn consumers_prevent_reaping() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+        runtime.system.inc_nonce(&alice);
+        runtime.system.inc_consumers(&alice);
+
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 95);
+        assert_eq!(
+            result,
+            Err("Account cannot be reaped while other entries depend on it.")
+        );
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.balance(&bob), 0);
+        assert!(runtime.system.account_exists(&alice));
+
+        runtime.system.dec_consumers(&alice);
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 95);
+        assert_eq!(result, Ok(()));
+        assert!(!runtime.system.account_exists(&alice));
+    }
+
+    #[test]
+    fn transfer_respects_existential_deposit() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+        runtime.system.inc_nonce(&alice);
+
+        // `bob` cannot be created with less than the existential deposit.
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 9);
+        assert_eq!(
+            result,
+            Err("Recipient would be left below the existential deposit.")
+        );
+        assert_eq!(balances.balance(&alice), 100);
+
+        // `alice` is kept alive with exactly the existential deposit.
+        assert_eq!(
+            balances.transfer(&mut runtime, alice.clone(), bob.clone(), 90),
+            Ok(())
+        );
+        assert_eq!(balances.balance(&alice), 10);
+        assert_eq!(balances.balances(None, 10).items.len(), 2);
+
+        // `alice` is reaped when left with dust, which is destroyed along with her nonce, while the
+        // account of `bob` was created along with its funds.
+        assert_eq!(
+            balances.transfer(&mut runtime, alice.clone(), bob.clone(), 5),
+            Ok(())
+        );
+        assert_eq!(balances.balance(&alice), 0);
+        assert_eq!(balances.balance(&bob), 95);
+        assert_eq!(balances.balances(None, 10).items, vec![(bob.clone(), 95)]);
+        assert_eq!(runtime.system.get_nonce(&alice), 0);
+        assert_eq!(
+            runtime.system.nonces(None, 10).items,
+            vec![(bob.clone(), 0)]
+        );
+        assert_eq!(runtime.system.providers(&bob), 1);
+    }
+
+    #[test]
+    fn root_calls_keep_total_issuance() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let root = "Root".to_string();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+
+        // Only root can create or destroy funds.
+        let result = balances.mint(&mut runtime, alice.clone(), alice.clone(), 100);
+        assert_eq!(result, Err("caller is not root"));
+        runtime.system.set_root(&root);
+
+        assert_eq!(
+            balances.mint(&mut runtime, root.clone(), alice.clone(), 100),
+            Ok(())
+        );
+        assert_eq!(
+            balances.mint(&mut runtime, root.clone(), bob.clone(), 50),
+            Ok(())
+        );
+        assert_eq!(balances.total_issuance(), 150);
+
+        let result = balances.burn(&mut runtime, alice.clone(), alice.clone(), 30);
+        assert_eq!(result, Err("caller is not root"));
+        assert_eq!(
+            balances.burn(&mut runtime, root.clone(), alice.clone(), 30),
+            Ok(())
+        );
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.total_issuance(), 120);
+
+        // Dust left by a burn is destroyed as well.
+        assert_eq!(
+            balances.burn(&mut runtime, root.clone(), bob.clone(), 45),
+            Ok(())
+        );
+        assert_eq!(balances.balance(&bob), 0);
+        assert_eq!(balances.total_issuance(), 70);
+
+        let result = balances.force_set_balance(&mut runtime, bob.clone(), alice.clone(), 1000);
+        assert_eq!(result, Err("caller is not root"));
+        let result = balances.force_set_balance(&mut runtime, root.clone(), alice.clone(), 1000);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.total_issuance(), 1000);
+
+        // Transfers move funds without changing the total issuance.
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 400);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.total_issuance(), 1000);
+        assert_eq!(balances.check_invariants(), Ok(()));
+
+        let result = balances.force_set_balance(&mut runtime, root.clone(), bob.clone(), 0);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.total_issuance(), 600);
+        assert_eq!(balances.check_invariants(), Ok(()));
+
+        // Writing a balance directly breaks the invariant.
+        balances.balances.insert(bob, 1);
+        assert_eq!(
+            balances.check_invariants(),
+            Err("sum of balances does not match the total issuance")
+        );
+    }
+
+    #[test]
+    fn transfer_variants() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let root = "Root".to_string();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        runtime.system.set_root(&root);
+        balances.set_balance(&alice, 100).unwrap();
+
+        // `transfer_keep_alive` fails instead of reaping `alice`.
+        let result = balances.transfer_keep_alive(&mut runtime, alice.clone(), bob.clone(), 95);
+        assert_eq!(result, Err("Transfer would kill the sender account."));
+        let result = balances.transfer_keep_alive(&mut runtime, alice.clone(), bob.clone(), 90);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 10);
+
+        // `transfer_all` leaves the locked funds, and the existential deposit if kept alive.
+        balances.set_balance(&alice, 100).unwrap();
+        balances.set_lock(*b"staking ", &alice, 30, Reasons::Transfer);
+        let result = balances.transfer_all(&mut runtime, alice.clone(), bob.clone(), true);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 30);
+        assert_eq!(balances.balance(&bob), 160);
+        balances.remove_lock(*b"staking ", &alice);
+        let result = balances.transfer_all(&mut runtime, alice.clone(), bob.clone(), true);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 10);
+        let result = balances.transfer_all(&mut runtime, alice.clone(), bob.clone(), false);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 0);
+        assert_eq!(balances.balance(&bob), 190);
+
+        // Only root can move funds out of other accounts.
+        let result =
+            balances.force_transfer(&mut runtime, alice.clone(), bob.clone(), alice.clone(), 50);
+        assert_eq!(result, Err("caller is not root"));
+        let result = balances.force_transfer(&mut runtime, root, bob.clone(), alice.clone(), 50);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 50);
+        assert_eq!(balances.balance(&bob), 140);
+        assert_eq!(balances.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn approvals() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        let charlie = "Charlie".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        let result = balances.cancel_approval(&mut runtime, alice.clone(), bob.clone());
+        assert_eq!(result, Err("No approval to cancel."));
+        let result = balances.transfer_approved(
+            &mut runtime,
+            bob.clone(),
+            alice.clone(),
+            charlie.clone(),
+            10,
+        );
+        assert_eq!(result, Err("Insufficient allowance."));
+
+        let result = balances.approve_transfer(&mut runtime, alice.clone(), bob.clone(), 50);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.approval(&alice, &bob), 50);
+
+        // Sending the funds back to their owner would spend the approval while moving nothing.
+        let events = runtime.system.events().len();
+        let result =
+            balances.transfer_approved(&mut runtime, bob.clone(), alice.clone(), alice.clone(), 50);
+        assert_eq!(
+            result,
+            Err("Cannot transfer approved funds back to their owner.")
+        );
+        assert_eq!(balances.approval(&alice, &bob), 50);
+        assert_eq!(runtime.system.events().len(), events);
+        let result = balances.transfer_approved(
+            &mut runtime,
+            bob.clone(),
+            alice.clone(),
+            charlie.clone(),
+            51,
+        );
+        assert_eq!(result, Err("Insufficient allowance."));
+        let result = balances.transfer_approved(
+            &mut runtime,
+            bob.clone(),
+            alice.clone(),
+            charlie.clone(),
+            30,
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.approval(&alice, &bob), 20);
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.balance(&charlie), 30);
+
+        // A failed transfer does not spend the allowance.
+        let result = balances.transfer_approved(
+            &mut runtime,
+            bob.clone(),
+            alice.clone(),
+            charlie.clone(),
+            5,
+        );
+        assert_eq!(result, Ok(()));
+        balances.set_lock(*b"staking ", &alice, 60, Reasons::All);
+        let result = balances.transfer_approved(
+            &mut runtime,
+            bob.clone(),
+            alice.clone(),
+            charlie.clone(),
+            15,
+        );
+        assert_eq!(result, Err("Balance is locked."));
+        assert_eq!(balances.approval(&alice, &bob), 15);
+
+        let result = balances.cancel_approval(&mut runtime, alice.clone(), bob.clone());
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.approval(&alice, &bob), 0);
+
+        assert_eq!(
+            runtime.system.events(),
+            &[
+                Event::Approved {
+                    owner: alice.clone(),
+                    delegate: bob.clone(),
+                    amount: 50
+                },
+                Event::Transfer {
+                    from: alice.clone(),
+                    to: charlie.clone(),
+                    amount: 30
+                },
+                Event::TransferredApproved {
+                    owner: alice.clone(),
+                    delegate: bob.clone(),
+                    to: charlie.clone(),
+                    amount: 30
+                },
+                Event::Transfer {
+                    from: alice.clone(),
+                    to: charlie.clone(),
+                    amount: 5
+                },
+                Event::TransferredApproved {
+                    owner: alice.clone(),
+                    delegate: bob.clone(),
+                    to: charlie,
+                    amount: 5
+                },
+                Event::ApprovalCancelled {
+                    owner: alice,
+                    delegate: bob
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_balances() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        assert_eq!(balances.reserve(&alice, 101), Err("Not enough funds."));
+        assert_eq!(balances.reserve(&alice, 60), Ok(()));
+        assert_eq!(balances.balance(&alice), 40);
+        assert_eq!(balances.reserved_balance(&alice), 60);
+        assert_eq!(balances.total_issuance(), 100);
+
+        // Reserved funds cannot be transferred, but keep the account alive.
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 50);
+        assert_eq!(result, Err("Not enough funds."));
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 40);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.balance(&alice), 0);
+        assert_eq!(balances.reserved_balance(&alice), 60);
+
+        // Only what is reserved can be unreserved or slashed.
+        assert_eq!(balances.unreserve(&alice, 10), 0);
+        assert_eq!(balances.slash_reserved(&alice, 20), 0);
+        assert_eq!(balances.reserved_balance(&alice), 30);
+        assert_eq!(balances.total_issuance(), 80);
+        assert_eq!(balances.slash_reserved(&alice, 50), 20);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+        assert_eq!(balances.balance(&alice), 10);
+        assert_eq!(balances.total_issuance(), 50);
+
+        balances.reserve(&bob, 30).unwrap();
+        let result = balances.repatriate_reserved(&bob, &alice, 20, BalanceStatus::Reserved);
+        assert_eq!(result, Ok(0));
+        let result = balances.repatriate_reserved(&bob, &alice, 20, BalanceStatus::Free);
+        assert_eq!(result, Ok(10));
+        assert_eq!(balances.balance(&alice), 20);
+        assert_eq!(balances.reserved_balance(&alice), 20);
+        assert_eq!(balances.reserved_balance(&bob), 0);
+        assert_eq!(balances.total_issuance(), 50);
+        assert_eq!(balances.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn locks_restrict_withdrawals() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        // Overlapping locks do not add up.
+        balances.set_lock(*b"staking ", &alice, 50, Reasons::Transfer);
+        balances.set_lock(*b"vesting ", &alice, 30, Reasons::All);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Transfer), 50);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Reserve), 30);
+
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 51);
+        assert_eq!(result, Err("Balance is locked."));
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 20);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.reserve(&alice, 51), Err("Balance is locked."));
+        assert_eq!(balances.reserve(&alice, 50), Ok(()));
+        balances.unreserve(&alice, 50);
+
+        balances.extend_lock(*b"vesting ", &alice, 20, Reasons::Transfer);
+        assert_eq!(
+            balances.locks(&alice)[1],
+            BalanceLock {
+                id: *b"vesting ",
+                amount: 30,
+                reasons: Reasons::All
+            }
+        );
+        balances.remove_lock(*b"staking ", &alice);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Transfer), 30);
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 50);
+        assert_eq!(result, Ok(()));
+
+        // Locks are removed when the account is reaped.
+        balances.remove_lock(*b"vesting ", &alice);
+        balances.set_lock(*b"staking ", &alice, 10, Reasons::Reserve);
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 25);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.locks(&alice), vec![]);
+    }
+
+    #[test]
+    fn holds_keep_their_funds() {
+        use TestReason::{Deposit, Staking};
+        let mut balances = Pallet::<TestRuntime>::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        assert_eq!(
+            balances.hold(Deposit, &alice, 101),
+            Err("Not enough funds.")
+        );
+        assert_eq!(balances.hold(Deposit, &alice, 20), Ok(()));
+        assert_eq!(balances.hold(Staking, &alice, 30), Ok(()));
+        assert_eq!(balances.hold(Deposit, &alice, 5), Ok(()));
+        assert_eq!(balances.reserve(&alice, 10), Ok(()));
+        assert_eq!(balances.balance_on_hold(Deposit, &alice), 25);
+        assert_eq!(balances.total_balance_on_hold(&alice), 55);
+        assert_eq!(balances.reserved_balance(&alice), 65);
+        assert_eq!(balances.balance(&alice), 35);
+
+        // Plain reserves cannot take held funds, nor can other holds.
+        assert_eq!(balances.slash_reserved(&alice, 20), 10);
+        assert_eq!(balances.unreserve(&alice, 10), 10);
+        assert_eq!(balances.release(Deposit, &alice, 30), 5);
+        assert_eq!(balances.balance_on_hold(Staking, &alice), 30);
+        assert_eq!(balances.balance(&alice), 60);
+        assert_eq!(
+            balances.holds(&alice),
+            vec![IdAmount {
+                id: Staking,
+                amount: 30
+            }]
+        );
+
+        assert_eq!(balances.burn_held(Staking, &alice, 10), 0);
+        assert_eq!(balances.total_issuance(), 80);
+        let result = balances.transfer_on_hold(Staking, &alice, &bob, 5, BalanceStatus::Free);
+        assert_eq!(
+            result,
+            Err("Recipient would be left below the existential deposit.")
+        );
+        let result = balances.transfer_on_hold(Staking, &alice, &bob, 15, BalanceStatus::Reserved);
+        assert_eq!(result, Ok(0));
+        let result = balances.transfer_on_hold(Staking, &alice, &bob, 15, BalanceStatus::Free);
+        assert_eq!(result, Ok(10));
+        assert_eq!(balances.balance_on_hold(Staking, &bob), 15);
+        assert_eq!(balances.balance(&bob), 5);
+        assert_eq!(balances.holds(&alice), vec![]);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+        assert_eq!(balances.total_issuance(), 80);
+        assert_eq!(balances.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn holds_are_released_on_schedule() {
+        use crate::support::Hooks;
+        use TestReason::{Deposit, Staking};
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let root = "Root".to_string();
+        runtime.system.set_root(&root);
+        assert_eq!(
+            balances.mint(&mut runtime, root.clone(), alice.clone(), 100),
+            Ok(())
+        );
+        let result = balances.with_references(&mut runtime, &[&alice], |pallet| {
+            pallet.hold(Deposit, &alice, 30)
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(runtime.system.consumers(&alice), 1);
+
+        assert_eq!(
+            balances.release_at(&mut runtime, Deposit, &alice, 20, 0),
+            Err("the release block must be in the future")
+        );
+        assert_eq!(
+            balances.release_at(&mut runtime, Staking, &alice, 20, 2),
+            Err("Not enough funds on hold.")
+        );
+        runtime.system.inc_block_number();
+        assert_eq!(
+            balances.release_at(&mut runtime, Deposit, &alice, 20, 2),
+            Ok(())
+        );
+        assert_eq!(
+            balances.release_at(&mut runtime, Deposit, &alice, 10, 2),
+            Ok(())
+        );
+        assert_eq!(balances.scheduled_releases(2).len(), 2);
+
+        // Nothing is released before the end of the scheduled block.
+        balances.on_finalize(&mut runtime);
+        assert_eq!(balances.balance_on_hold(Deposit, &alice), 30);
+        runtime.system.inc_block_number();
+        balances.on_finalize(&mut runtime);
+        assert_eq!(balances.balance_on_hold(Deposit, &alice), 0);
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.scheduled_releases(2), vec![]);
+        assert_eq!(runtime.system.consumers(&alice), 0);
+    }
+
+    #[test]
+    fn freezes_restrict_withdrawals() {
+        use TestReason::{Deposit, Staking};
+        let mut balances = Pallet::<TestRuntime>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        balances.set_balance(&alice, 100).unwrap();
+
+        // Freezes overlap with each other and with locks, and prevent any withdrawal.
+        balances.set_freeze(Staking, &alice, 40);
+        balances.extend_freeze(Staking, &alice, 30);
+        balances.set_lock(*b"vesting ", &alice, 50, Reasons::Transfer);
+        assert_eq!(balances.balance_frozen(Staking, &alice), 40);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Transfer), 50);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Reserve), 40);
+        assert_eq!(
+            balances.hold(Deposit, &alice, 61),
+            Err("Balance is locked.")
+        );
+
+        balances.extend_freeze(Staking, &alice, 70);
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 31);
+        assert_eq!(result, Err("Balance is locked."));
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 30);
+        assert_eq!(result, Ok(()));
+
+        balances.thaw(Staking, &alice);
+        assert_eq!(balances.freezes(&alice), vec![]);
+        assert_eq!(balances.frozen_balance(&alice, Reasons::Reserve), 0);
+
+        // Holds and freezes are removed when the account is reaped.
+        balances.remove_lock(*b"vesting ", &alice);
+        balances.set_freeze(Deposit, &alice, 10);
+        assert_eq!(balances.hold(Deposit, &alice, 5), Ok(()));
+        balances.thaw(Deposit, &alice);
+        let result = balances.transfer(&mut runtime, alice.clone(), bob.clone(), 65);
+        assert_eq!(result, Ok(()));
+        assert_eq!(balances.holds(&alice), vec![]);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+        assert_eq!(balances.total_issuance(), 95);
+    }
+
+    #[test]
+    fn currency_through_runtime() {
+        type Balances = Pallet<TestRuntime>;
+        let mut runtime = TestRuntime::new();
+        let alice = "Alice".to_string();
+        let bob = "Bob".to_string();
+        runtime.balances.set_balance(&alice, 100).unwrap();
+        runtime.system.inc_nonce(&alice);
+
+        let result = <Balances as ReservableCurrency<_>>::reserve(&mut runtime, &alice, 30);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            <Balances as Currency<_>>::free_balance(&runtime, &alice),
+            70
+        );
+        assert_eq!(
+            <Balances as ReservableCurrency<_>>::reserved_balance(&runtime, &alice),
+            30
+        );
+
+        <Balances as LockableCurrency<_>>::set_lock(
+            &mut runtime,
+            *b"staking ",
+            &alice,
+            70,
+            Reasons::All,
+        );
+        let result = <Balances as Currency<_>>::transfer(&mut runtime, &alice, &bob, 10, false);
+        assert_eq!(result, Err("Balance is locked."));
+        <Balances as LockableCurrency<_>>::remove_lock(&mut runtime, *b"staking ", &alice);
+
+        let result = <Balances as ReservableCurrency<_>>::repatriate_reserved(
+            &mut runtime,
+            &alice,
+            &bob,
+            30,
+            BalanceStatus::Free,
+        );
+        assert_eq!(result, Ok(0));
+        assert_eq!(<Balances as Currency<_>>::free_balance(&runtime, &bob), 30);
+
+        // Reaping through the trait also clears the `system` entries of the account.
+        let result = <Balances as Currency<_>>::transfer(&mut runtime, &alice, &bob, 65, true);
+        assert_eq!(result, Err("Transfer would kill the sender account."));
+        let result = <Balances as Currency<_>>::transfer(&mut runtime, &alice, &bob, 65, false);
+        assert_eq!(result, Ok(()));
+        assert_eq!(runtime.system.get_nonce(&alice), 0);
+        assert_eq!(<Balances as Currency<_>>::free_balance(&runtime, &bob), 95);
+        assert_eq!(<Balances as Currency<_>>::total_issuance(&runtime), 95);
+
+        let result =
+            <Balances as HoldableCurrency<_>>::hold(&mut runtime, TestReason::Deposit, &bob, 20);
+        assert_eq!(result, Ok(()));
+        <Balances as FreezableCurrency<_>>::set_freeze(&mut runtime, TestReason::Staking, &bob, 75);
+        let result = <Balances as Currency<_>>::transfer(&mut runtime, &bob, &alice, 10, false);
+        assert_eq!(result, Err("Balance is locked."));
+        <Balances as FreezableCurrency<_>>::thaw(&mut runtime, TestReason::Staking, &bob);
+        let result = <Balances as HoldableCurrency<_>>::transfer_on_hold(
+            &mut runtime,
+            TestReason::Deposit,
+            &bob,
+            &alice,
+            20,
+            BalanceStatus::Free,
+        );
+        assert_eq!(result, Ok(0));
+        assert_eq!(
+            <Balances as HoldableCurrency<_>>::balance_on_hold(&runtime, TestReason::Deposit, &bob),
+            0
+        );
+        assert_eq!(
+            <Balances as Currency<_>>::free_balance(&runtime, &alice),
+            20
+        );
+    }
+
+    #[test]
+    fn accounts_without_funds_are_reaped() {
+        type Balances = Pallet<TestRuntime>;
+        let mut runtime = TestRuntime::new();
+        let root = "Root".to_string();
+        let alice = "Alice".to_string();
+        runtime.system.set_root(&root);
+        let mint = |runtime: &mut TestRuntime| {
+            with_pallet(runtime, |pallet, runtime| {
+                pallet.mint(runtime, root.clone(), alice.clone(), 100)
+            })
+        };
+        assert_eq!(mint(&mut runtime), Ok(()));
+        assert_eq!(runtime.system.providers(&alice), 1);
+
+        // Slashing all of its funds reaps the account, dropping the references on it.
+        let result = <Balances as ReservableCurrency<_>>::reserve(&mut runtime, &alice, 100);
+        assert_eq!(result, Ok(()));
+        assert_eq!(runtime.system.consumers(&alice), 1);
+        let result = <Balances as ReservableCurrency<_>>::slash_reserved(&mut runtime, &alice, 100);
+        assert_eq!(result, 0);
+        assert!(!runtime.system.account_exists(&alice));
+
+        // Funding it again provides for it once.
+        assert_eq!(mint(&mut runtime), Ok(()));
+        assert_eq!(runtime.system.providers(&alice), 1);
+        assert_eq!(runtime.system.consumers(&alice), 0);
+
+        // An account another entry depends on keeps its provider once emptied, and is not given a
+        // second one when funded again.
+        runtime.system.inc_consumers(&alice);
+        let result = <Balances as ReservableCurrency<_>>::reserve(&mut runtime, &alice, 100);
+        assert_eq!(result, Ok(()));
+        let result = <Balances as ReservableCurrency<_>>::slash_reserved(&mut runtime, &alice, 100);
+        assert_eq!(result, 0);
+        assert_eq!(runtime.system.providers(&alice), 1);
+        assert_eq!(runtime.system.consumers(&alice), 1);
+        assert_eq!(mint(&mut runtime), Ok(()));
+        assert_eq!(runtime.system.providers(&alice), 1);
+
+        // Once nothing depends on it anymore, emptying it reaps it.
+        runtime.system.dec_consumers(&alice);
+        let result = <Balances as ReservableCurrency<_>>::reserve(&mut runtime, &alice, 100);
+        assert_eq!(result, Ok(()));
+        <Balances as ReservableCurrency<_>>::slash_reserved(&mut runtime, &alice, 100);
+        assert!(!runtime.system.account_exists(&alice));
+    }
+
+    #[test]
+    fn balances_are_paginated() {
+        let mut balances = Pallet::<TestRuntime>::new();
+        balances.set_balance(&"alice".to_string(), 100).unwrap();
+        balances.set_balance(&"bob".to_string(), 50).unwrap();
+        balances.set_balance(&"charlie".to_string(), 20).unwrap();
+
+        let page = balances.balances(None, 2);
+        assert_eq!(
+            page.items,
+            vec![("alice".to_string(), 100), ("bob".to_string(), 50)]
+        );
+        assert_eq!(page.next, Some("bob".to_string()));
+        let page = balances.balances(page.next.as_ref(), 2);
+        assert_eq!(page.items, vec![("charlie".to_string(), 20)]);
+        assert_eq!(page.next, None);
+    }
 }
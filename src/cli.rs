@@ -0,0 +1,331 @@
+//! The commands of the binary, run instead of the demo chain when it is given arguments.
+//!
+//! The commands looking up claims read the claims database, which the demo chain saves once it
+//! has produced its blocks. They never produce blocks themselves.
+
+use crate::support::hashing::{from_hex, to_hex, Digest};
+use crate::support::merkle::{self, MerkleProof};
+use crate::{proof_of_existence, types, Runtime};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage:
+  merkle-prove <index> <file>...     print the Merkle root of the files, and the proof that the
+                                     file at <index> is included
+  merkle-verify <file> <root> <proof> [<database>]
+                                     check that <proof> shows the file to be included under <root>,
+                                     and whether <root> is claimed
+  claim-status <file> [<database>]   print whether the file is claimed, by whom and since when
+
+<database> defaults to the one saved by the demo chain, at $CLAIMS_DATABASE if it is set, or
+rust-state-machine-claims.db in the current directory otherwise.";
+
+/// The variable setting the path of the claims database saved by the demo chain.
+const DATABASE_VAR: &str = "CLAIMS_DATABASE";
+
+/// The first line of the claims database, followed by a line for each claim. The accounts are
+/// escaped, so that they can hold any character, and the co-owners are separated by commas.
+const DATABASE_HEADER: &str = "# digest\tcreated at\towner\tco-owners";
+
+/// A claim, as saved in the claims database.
+#[derive(Debug, PartialEq)]
+pub struct SavedClaim {
+    pub owner: types::AccountId,
+    pub co_owners: Vec<types::AccountId>,
+    pub created_at: types::BlockNumber,
+}
+
+/// Run the command given by `args`, returning the error to report if it fails.
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args {
+        [command, index, files @ ..] if command == "merkle-prove" && !files.is_empty() => {
+            let index = index
+                .parse()
+                .map_err(|_| format!("invalid index `{}`", index))?;
+            let documents = files
+                .iter()
+                .map(|file| hash_file(file))
+                .collect::<Result<Vec<_>, _>>()?;
+            let proof = merkle::prove(hashing(), &documents, index)
+                .ok_or(format!("no file at index {}", index))?;
+            println!("root: {}", to_hex(&merkle::root(hashing(), &documents)));
+            println!("proof: {}", proof);
+            Ok(())
+        }
+        [command, file, root, proof, database @ ..]
+            if command == "merkle-verify" && database.len() <= 1 =>
+        {
+            let document = hash_file(file)?;
+            let root = from_hex(root).ok_or(format!("invalid root `{}`", root))?;
+            let proof: MerkleProof = proof.parse()?;
+            if !merkle::verify(hashing(), &root, &document, &proof) {
+                return Err(format!("{} is not included under {}", file, to_hex(&root)));
+            }
+            println!("{} is included under {}", file, to_hex(&root));
+            let claims = load_claims(&database_path(database))?;
+            print_status(&to_hex(&root), claims.get(&root));
+            Ok(())
+        }
+        [command, file, database @ ..] if command == "claim-status" && database.len() <= 1 => {
+            let claim = hash_file(file)?;
+            let claims = load_claims(&database_path(database))?;
+            print_status(
+                &format!("{} ({})", file, to_hex(&claim)),
+                claims.get(&claim),
+            );
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// The path of the claims database saved by the demo chain, set by `CLAIMS_DATABASE` or in the
+/// current directory.
+pub fn default_database() -> PathBuf {
+    std::env::var_os(DATABASE_VAR).map_or_else(
+        || PathBuf::from("rust-state-machine-claims.db"),
+        PathBuf::from,
+    )
+}
+
+/// Save the claims of `runtime` as of its latest block to the claims database at `path`,
+/// replacing it.
+pub fn save_claims(runtime: &Runtime, path: &Path) -> std::io::Result<()> {
+    let mut lines = vec![DATABASE_HEADER.to_string()];
+    let mut cursor = None;
+    loop {
+        let page = runtime.proof_of_existence.claims(cursor.as_ref(), 100);
+        for (claim, info) in &page.items {
+            lines.push(format!(
+                "{}\t{}\t{}\t{}",
+                to_hex(claim),
+                info.created_at,
+                escape(&info.owner),
+                info.co_owners
+                    .iter()
+                    .map(|co_owner| escape(co_owner))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ));
+        }
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    // Write the whole database aside first, so that it is never seen half written.
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, lines.join("\n") + "\n")?;
+    std::fs::rename(partial, path)
+}
+
+/// Load the claims database at `path`.
+pub fn load_claims(path: &Path) -> Result<BTreeMap<Digest, SavedClaim>, String> {
+    let database = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "cannot read the claims database {}: {}, run the demo chain to create it",
+            path.display(),
+            e
+        )
+    })?;
+    let malformed = |line: &str| format!("malformed line in the claims database: `{}`", line);
+    let mut claims = BTreeMap::new();
+    for line in database.lines().filter(|line| *line != DATABASE_HEADER) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [claim, created_at, owner, co_owners] = fields[..] else {
+            return Err(malformed(line));
+        };
+        let claim = from_hex(claim).ok_or_else(|| malformed(line))?;
+        let owner = match unescape_list(owner).as_deref() {
+            Some([owner]) => owner.clone(),
+            _ => return Err(malformed(line)),
+        };
+        let co_owners = match co_owners {
+            "" => Vec::new(),
+            co_owners => unescape_list(co_owners).ok_or_else(|| malformed(line))?,
+        };
+        let saved = SavedClaim {
+            owner,
+            co_owners,
+            created_at: created_at.parse().map_err(|_| malformed(line))?,
+        };
+        claims.insert(claim, saved);
+    }
+    Ok(claims)
+}
+
+/// Escape `account` for the claims database, so that it holds no tab, line break or comma.
+fn escape(account: &str) -> String {
+    let mut escaped = String::with_capacity(account.len());
+    for c in account.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            ',' => escaped.push_str("\\,"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Split `field` of the claims database at its unescaped commas, and unescape the accounts in
+/// it. Returns `None` if an escape is invalid.
+fn unescape_list(field: &str) -> Option<Vec<String>> {
+    let mut accounts = vec![String::new()];
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            ',' => {
+                accounts.push(String::new());
+                continue;
+            }
+            '\\' => match chars.next()? {
+                't' => '\t',
+                'n' => '\n',
+                'r' => '\r',
+                c @ ('\\' | ',') => c,
+                _ => return None,
+            },
+            c => c,
+        };
+        accounts.last_mut()?.push(c);
+    }
+    Some(accounts)
+}
+
+/// The claims database given on the command line, or the default one.
+fn database_path(database: &[String]) -> PathBuf {
+    database
+        .first()
+        .map_or_else(default_database, PathBuf::from)
+}
+
+/// Print whether `what` is claimed, and the details of its `claim` if it is.
+fn print_status(what: &str, claim: Option<&SavedClaim>) {
+    match claim {
+        None => println!("{} is not claimed", what),
+        Some(claim) if claim.co_owners.is_empty() => println!(
+            "{} is claimed by {} since block {}",
+            what, claim.owner, claim.created_at
+        ),
+        Some(claim) => println!(
+            "{} is claimed by {} with {} since block {}",
+            what,
+            claim.owner,
+            claim.co_owners.join(", "),
+            claim.created_at
+        ),
+    }
+}
+
+/// The hash function of the claims of the runtime.
+fn hashing() -> crate::support::hashing::Hashing {
+    <Runtime as proof_of_existence::Config>::HASHING
+}
+
+fn hash_file(file: &str) -> Result<Digest, String> {
+    proof_of_existence::Pallet::<Runtime>::hash_file(file)
+        .map_err(|e| format!("cannot read {}: {}", file, e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{balances, support, RuntimeCall};
+
+    /// Execute a block of `calls` with their callers as the next block of `runtime`.
+    fn execute(runtime: &mut Runtime, calls: Vec<(types::AccountId, RuntimeCall)>) {
+        let block = types::Block {
+            header: support::Header {
+                block_number: runtime.system.block_number() + 1,
+            },
+            extrinsics: calls
+                .into_iter()
+                .map(|(caller, call)| support::Extrinsic { caller, call })
+                .collect(),
+        };
+        runtime.execute_block(block).expect("valid block");
+    }
+
+    #[test]
+    fn claims_are_saved_and_loaded() {
+        let mut runtime = Runtime::new();
+        let alice = "Alice".to_string();
+        runtime.system.set_root(&"Root".to_string());
+        let mint = RuntimeCall::balances(balances::Call::mint {
+            to: alice.clone(),
+            amount: 100,
+        });
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: proof_of_existence::Claim::Content(b"deed".to_vec()),
+            expires_at: None,
+        });
+        execute(
+            &mut runtime,
+            vec![("Root".to_string(), mint), (alice.clone(), claim)],
+        );
+
+        let path = std::env::temp_dir().join("cli_claims_are_saved_and_loaded.db");
+        save_claims(&runtime, &path).unwrap();
+        let claims = load_claims(&path).unwrap();
+        std::fs::write(&path, "not a claim\n").unwrap();
+        let malformed = load_claims(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let claim = proof_of_existence::Pallet::<Runtime>::hash(b"deed");
+        let saved = SavedClaim {
+            owner: alice,
+            co_owners: vec![],
+            created_at: 1,
+        };
+        assert_eq!(claims.into_iter().collect::<Vec<_>>(), vec![(claim, saved)]);
+        assert_eq!(
+            malformed,
+            Err("malformed line in the claims database: `not a claim`".to_string())
+        );
+    }
+
+    #[test]
+    fn saved_accounts_are_escaped() {
+        let mut runtime = Runtime::new();
+        let owner = "Alice,\tof\nWonderland\\".to_string();
+        let co_owners = vec!["Bob,".to_string(), "\\,Charlie\r\n".to_string()];
+        runtime.system.set_root(&"Root".to_string());
+        let mut calls: Vec<_> = [&owner, &co_owners[0], &co_owners[1]]
+            .into_iter()
+            .map(|to| {
+                let mint = balances::Call::mint {
+                    to: to.clone(),
+                    amount: 100,
+                };
+                ("Root".to_string(), RuntimeCall::balances(mint))
+            })
+            .collect();
+        let claim = proof_of_existence::Call::create_joint_claim {
+            claim: proof_of_existence::Claim::Content(b"deed".to_vec()),
+            co_owners: co_owners.clone(),
+            threshold: 2,
+            expires_at: None,
+        };
+        calls.push((owner.clone(), RuntimeCall::proof_of_existence(claim)));
+        execute(&mut runtime, calls);
+
+        let path = std::env::temp_dir().join("cli_saved_accounts_are_escaped.db");
+        save_claims(&runtime, &path).unwrap();
+        let database = std::fs::read_to_string(&path).unwrap();
+        let claims = load_claims(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(database.lines().count(), 2);
+        let claim = proof_of_existence::Pallet::<Runtime>::hash(b"deed");
+        let saved = SavedClaim {
+            owner,
+            co_owners,
+            created_at: 1,
+        };
+        assert_eq!(claims.into_iter().collect::<Vec<_>>(), vec![(claim, saved)]);
+        assert_eq!(unescape_list("Alice\\x"), None);
+    }
+}
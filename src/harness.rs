@@ -0,0 +1,569 @@
+//! A property-based test harness for the whole runtime.
+//!
+//! It generates random blocks of calls, runs them through `Runtime::execute_block_inspecting`, and
+//! checks the runtime invariants after every extrinsic and at the end of every block. A failing
+//! case is shrunk to a minimal reproducer before being reported.
+
+use crate::support::DispatchResult;
+use crate::{assets, balances, proof_of_existence, support, types, vesting, Runtime, RuntimeCall};
+
+/// The accounts making calls, which all get funds and assets at genesis.
+const ACCOUNTS: [&str; 4] = ["Alice", "Bob", "Charlie", "Dave"];
+/// The content which can be claimed.
+const CLAIMS: [&str; 3] = ["a", "b", "c"];
+/// The asset created at genesis, administered by the first account.
+const ASSET: types::AssetId = 0;
+/// The funds and amount of the asset each account gets at genesis.
+const ENDOWMENT: types::Balance = 100;
+
+/// A small deterministic random number generator (xorshift64*), so that a failing case can be
+/// generated again from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn account(&mut self) -> usize {
+        self.below(ACCOUNTS.len() as u64) as usize
+    }
+
+    fn amount(&mut self) -> types::Balance {
+        self.below(ENDOWMENT as u64 + 20) as types::Balance
+    }
+}
+
+/// A call made by one of the `ACCOUNTS`, with accounts given by their index. Unlike `RuntimeCall`,
+/// it can be cloned, printed and shrunk.
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Transfer {
+        to: usize,
+        amount: types::Balance,
+    },
+    TransferKeepAlive {
+        to: usize,
+        amount: types::Balance,
+    },
+    TransferAll {
+        to: usize,
+        keep_alive: bool,
+    },
+    ApproveTransfer {
+        delegate: usize,
+        amount: types::Balance,
+    },
+    TransferApproved {
+        owner: usize,
+        to: usize,
+        amount: types::Balance,
+    },
+    CreateClaim {
+        claim: usize,
+        expires_at: Option<types::BlockNumber>,
+    },
+    RevokeClaim {
+        claim: usize,
+    },
+    TransferClaim {
+        claim: usize,
+        to: usize,
+    },
+    /// A claim co-owned with `co_owner`, which both must approve changing.
+    CreateJointClaim {
+        claim: usize,
+        co_owner: usize,
+    },
+    VestedTransfer {
+        to: usize,
+        locked: types::Balance,
+        per_block: types::Balance,
+    },
+    Vest,
+    AssetTransfer {
+        to: usize,
+        amount: types::Balance,
+    },
+}
+
+impl Op {
+    fn random(rng: &mut Rng) -> Self {
+        match rng.below(12) {
+            0 => Op::Transfer {
+                to: rng.account(),
+                amount: rng.amount(),
+            },
+            1 => Op::TransferKeepAlive {
+                to: rng.account(),
+                amount: rng.amount(),
+            },
+            2 => Op::TransferAll {
+                to: rng.account(),
+                keep_alive: rng.below(2) == 0,
+            },
+            3 => Op::ApproveTransfer {
+                delegate: rng.account(),
+                amount: rng.amount(),
+            },
+            4 => Op::TransferApproved {
+                owner: rng.account(),
+                to: rng.account(),
+                amount: rng.amount(),
+            },
+            5 => Op::CreateClaim {
+                claim: rng.below(CLAIMS.len() as u64) as usize,
+                // Blocks are numbered from 2, so some claims expire within the case.
+                expires_at: match rng.below(5) {
+                    0 => None,
+                    n => Some(n as types::BlockNumber + 1),
+                },
+            },
+            6 => Op::RevokeClaim {
+                claim: rng.below(CLAIMS.len() as u64) as usize,
+            },
+            7 => Op::VestedTransfer {
+                to: rng.account(),
+                locked: rng.amount(),
+                per_block: rng.below(10) as types::Balance,
+            },
+            8 => Op::Vest,
+            9 => Op::TransferClaim {
+                claim: rng.below(CLAIMS.len() as u64) as usize,
+                to: rng.account(),
+            },
+            10 => Op::CreateJointClaim {
+                claim: rng.below(CLAIMS.len() as u64) as usize,
+                co_owner: rng.account(),
+            },
+            _ => Op::AssetTransfer {
+                to: rng.account(),
+                amount: rng.amount(),
+            },
+        }
+    }
+
+    /// Simpler versions of this call, with smaller amounts.
+    fn simpler(&self) -> Vec<Op> {
+        let smaller = |amount: types::Balance| {
+            [0, amount / 2, amount.saturating_sub(1)]
+                .into_iter()
+                .filter(move |smaller| *smaller < amount)
+        };
+        match self.clone() {
+            Op::Transfer { to, amount } => smaller(amount)
+                .map(|amount| Op::Transfer { to, amount })
+                .collect(),
+            Op::TransferKeepAlive { to, amount } => smaller(amount)
+                .map(|amount| Op::TransferKeepAlive { to, amount })
+                .collect(),
+            Op::ApproveTransfer { delegate, amount } => smaller(amount)
+                .map(|amount| Op::ApproveTransfer { delegate, amount })
+                .collect(),
+            Op::TransferApproved { owner, to, amount } => smaller(amount)
+                .map(|amount| Op::TransferApproved { owner, to, amount })
+                .collect(),
+            Op::VestedTransfer {
+                to,
+                locked,
+                per_block,
+            } => smaller(locked)
+                .map(|locked| Op::VestedTransfer {
+                    to,
+                    locked,
+                    per_block,
+                })
+                .collect(),
+            Op::AssetTransfer { to, amount } => smaller(amount)
+                .map(|amount| Op::AssetTransfer { to, amount })
+                .collect(),
+            Op::CreateClaim {
+                claim,
+                expires_at: Some(_),
+            } => vec![Op::CreateClaim {
+                claim,
+                expires_at: None,
+            }],
+            Op::TransferAll { .. }
+            | Op::CreateClaim { .. }
+            | Op::RevokeClaim { .. }
+            | Op::TransferClaim { .. }
+            | Op::CreateJointClaim { .. }
+            | Op::Vest => vec![],
+        }
+    }
+
+    fn call(&self) -> RuntimeCall {
+        let account = |index: usize| ACCOUNTS[index].to_string();
+        match self.clone() {
+            Op::Transfer { to, amount } => RuntimeCall::balances(balances::Call::transfer {
+                to: account(to),
+                amount,
+            }),
+            Op::TransferKeepAlive { to, amount } => {
+                RuntimeCall::balances(balances::Call::transfer_keep_alive {
+                    to: account(to),
+                    amount,
+                })
+            }
+            Op::TransferAll { to, keep_alive } => {
+                RuntimeCall::balances(balances::Call::transfer_all {
+                    to: account(to),
+                    keep_alive,
+                })
+            }
+            Op::ApproveTransfer { delegate, amount } => {
+                RuntimeCall::balances(balances::Call::approve_transfer {
+                    delegate: account(delegate),
+                    amount,
+                })
+            }
+            Op::TransferApproved { owner, to, amount } => {
+                RuntimeCall::balances(balances::Call::transfer_approved {
+                    owner: account(owner),
+                    to: account(to),
+                    amount,
+                })
+            }
+            Op::CreateClaim { claim, expires_at } => {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                    claim: proof_of_existence::Claim::Content(CLAIMS[claim].as_bytes().to_vec()),
+                    expires_at,
+                })
+            }
+            Op::RevokeClaim { claim } => {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+                    claim: proof_of_existence::Claim::Content(CLAIMS[claim].as_bytes().to_vec()),
+                })
+            }
+            Op::TransferClaim { claim, to } => {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::transfer_claim {
+                    claim: proof_of_existence::Claim::Content(CLAIMS[claim].as_bytes().to_vec()),
+                    new_owner: account(to),
+                })
+            }
+            Op::CreateJointClaim { claim, co_owner } => {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::create_joint_claim {
+                    claim: proof_of_existence::Claim::Content(CLAIMS[claim].as_bytes().to_vec()),
+                    co_owners: vec![account(co_owner)],
+                    threshold: 2,
+                    expires_at: None,
+                })
+            }
+            Op::VestedTransfer {
+                to,
+                locked,
+                per_block,
+            } => RuntimeCall::vesting(vesting::Call::vested_transfer {
+                target: account(to),
+                schedule: vesting::VestingInfo {
+                    locked,
+                    per_block,
+                    starting_block: 3,
+                },
+            }),
+            Op::Vest => RuntimeCall::vesting(vesting::Call::vest {}),
+            Op::AssetTransfer { to, amount } => RuntimeCall::assets(assets::Call::transfer {
+                id: ASSET,
+                target: account(to),
+                amount,
+            }),
+        }
+    }
+}
+
+/// An `Op` made by the account at index `caller`.
+#[derive(Debug, Clone, PartialEq)]
+struct Extrinsic {
+    caller: usize,
+    op: Op,
+}
+
+/// A sequence of blocks, executed after genesis.
+type Case = Vec<Vec<Extrinsic>>;
+
+/// An extra invariant to check after every extrinsic, on top of the runtime ones.
+type Check = dyn Fn(&Runtime) -> DispatchResult;
+
+fn random_case(seed: u64) -> Case {
+    let mut rng = Rng::new(seed);
+    let blocks = 1 + rng.below(4);
+    (0..blocks)
+        .map(|_| {
+            let extrinsics = 1 + rng.below(8);
+            (0..extrinsics)
+                .map(|_| Extrinsic {
+                    caller: rng.account(),
+                    op: Op::random(&mut rng),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Create a runtime, where every account has been given funds and some of an asset.
+fn genesis() -> Runtime {
+    let mut runtime = Runtime::new();
+    let root = "Root".to_string();
+    let admin = ACCOUNTS[0].to_string();
+    runtime.system.set_root(&root);
+
+    let mut extrinsics = vec![support::Extrinsic {
+        caller: admin.clone(),
+        call: RuntimeCall::assets(assets::Call::create {
+            id: ASSET,
+            admin: admin.clone(),
+            min_balance: 1,
+        }),
+    }];
+    for account in ACCOUNTS {
+        extrinsics.push(support::Extrinsic {
+            caller: root.clone(),
+            call: RuntimeCall::balances(balances::Call::mint {
+                to: account.to_string(),
+                amount: ENDOWMENT,
+            }),
+        });
+        extrinsics.push(support::Extrinsic {
+            caller: admin.clone(),
+            call: RuntimeCall::assets(assets::Call::mint {
+                id: ASSET,
+                beneficiary: account.to_string(),
+                amount: ENDOWMENT,
+            }),
+        });
+    }
+    let block = types::Block {
+        header: support::Header { block_number: 1 },
+        extrinsics,
+    };
+    let receipts = runtime
+        .execute_block(block)
+        .expect("genesis block is valid");
+    assert!(
+        receipts.iter().all(|receipt| receipt.result.is_ok()),
+        "genesis calls succeed"
+    );
+    runtime
+}
+
+/// Run `case` after genesis, checking after every extrinsic that the runtime invariants and
+/// `check` hold, and that no funds or assets were created. Returns a description of the first
+/// failure.
+fn run(case: &Case, check: &Check) -> Result<(), String> {
+    let mut runtime = genesis();
+    let mut issuance = runtime.balances.total_issuance();
+    let supply = runtime.assets.asset(ASSET).map(|details| details.supply);
+    let mut failure = None;
+
+    for (i, extrinsics) in case.iter().enumerate() {
+        let block_number = i as types::BlockNumber + 2;
+        let block = types::Block {
+            header: support::Header { block_number },
+            extrinsics: extrinsics
+                .iter()
+                .map(|extrinsic| support::Extrinsic {
+                    caller: ACCOUNTS[extrinsic.caller].to_string(),
+                    call: extrinsic.op.call(),
+                })
+                .collect(),
+        };
+        runtime
+            .execute_block_inspecting(block, |runtime, receipt| {
+                if failure.is_some() {
+                    return;
+                }
+                // Funds are only ever destroyed as the dust of a reaped account.
+                let new_issuance = runtime.balances.total_issuance();
+                let error = if new_issuance > issuance {
+                    Some("funds were created".to_string())
+                } else if issuance - new_issuance
+                    >= <Runtime as balances::Config>::EXISTENTIAL_DEPOSIT
+                {
+                    Some("more funds than dust were destroyed".to_string())
+                } else if runtime.assets.asset(ASSET).map(|details| details.supply) != supply {
+                    Some("the supply of the asset changed".to_string())
+                } else if let Err((invariant, e)) = runtime.check_invariants() {
+                    Some(format!("invariant `{}` does not hold: {}", invariant, e))
+                } else {
+                    check(runtime).err().map(|e| e.to_string())
+                };
+                issuance = new_issuance;
+                failure = error.map(|e| {
+                    format!(
+                        "block {} extrinsic {}: {}",
+                        block_number, receipt.extrinsic_index, e
+                    )
+                });
+            })
+            .expect("generated blocks are valid");
+        if let Some(failure) = failure {
+            return Err(failure);
+        }
+        // The hooks run at the end of the block are not inspected with the extrinsics.
+        if let Err((invariant, e)) = runtime.check_invariants() {
+            return Err(format!(
+                "block {} finalization: invariant `{}` does not hold: {}",
+                block_number, invariant, e
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Shrink a failing `case` to one which still fails, but where no block or extrinsic can be
+/// removed and no amount can be reduced. Returns it with its failure.
+fn shrink(mut case: Case, check: &Check) -> (Case, String) {
+    let mut failure = run(&case, check).expect_err("only failing cases are shrunk");
+    loop {
+        let candidate = simpler_cases(&case)
+            .into_iter()
+            .find_map(|candidate| run(&candidate, check).err().map(|e| (candidate, e)));
+        match candidate {
+            Some((candidate, e)) => {
+                case = candidate;
+                failure = e;
+            }
+            None => return (case, failure),
+        }
+    }
+}
+
+/// All the cases one step simpler than `case`, simplest first.
+fn simpler_cases(case: &Case) -> Vec<Case> {
+    let mut cases = Vec::new();
+    for block in 0..case.len() {
+        let mut candidate = case.clone();
+        candidate.remove(block);
+        cases.push(candidate);
+    }
+    for block in 0..case.len() {
+        for extrinsic in 0..case[block].len() {
+            let mut candidate = case.clone();
+            candidate[block].remove(extrinsic);
+            cases.push(candidate);
+        }
+    }
+    for block in 0..case.len() {
+        for extrinsic in 0..case[block].len() {
+            for op in case[block][extrinsic].op.simpler() {
+                let mut candidate = case.clone();
+                candidate[block][extrinsic].op = op;
+                cases.push(candidate);
+            }
+        }
+    }
+    cases
+}
+
+/// Run random cases from `seeds`, panicking with a minimal reproducer of the first failure.
+fn check_random_cases(seeds: std::ops::Range<u64>, check: &Check) {
+    for seed in seeds {
+        let case = random_case(seed);
+        if run(&case, check).is_err() {
+            let (case, failure) = shrink(case, check);
+            panic!(
+                "seed {}: {}\nminimal reproducer: {:#?}",
+                seed, failure, case
+            );
+        }
+    }
+}
+
+#[test]
+fn random_blocks_keep_invariants() {
+    check_random_cases(0..200, &|_| Ok(()));
+}
+
+#[test]
+fn failures_are_shrunk() {
+    // `Bob` can receive funds, so holding less than the endowment is not an invariant.
+    let check: &Check = &|runtime| {
+        if runtime.balances.balance(&"Bob".to_string()) > ENDOWMENT {
+            return Err("Bob received funds");
+        }
+        Ok(())
+    };
+    let seed = (0..100)
+        .find(|seed| run(&random_case(*seed), check).is_err())
+        .expect("some random case sends funds to Bob");
+
+    let (case, failure) = shrink(random_case(seed), check);
+    assert!(failure.ends_with("Bob received funds"), "{}", failure);
+    // Sending funds to `Bob` takes at most an approval and a transfer.
+    assert!(case.iter().flatten().count() <= 2, "{:#?}", case);
+    assert!(simpler_cases(&case)
+        .iter()
+        .all(|candidate| run(candidate, check).is_ok()));
+}
+
+#[test]
+fn failed_calls_keep_their_writes() {
+    let mut runtime = genesis();
+    let alice = ACCOUNTS[0].to_string();
+    let bob = ACCOUNTS[1].to_string();
+    let nonce = runtime.system.get_nonce(&alice);
+    let block = types::Block {
+        header: support::Header { block_number: 2 },
+        extrinsics: vec![support::Extrinsic {
+            caller: alice.clone(),
+            call: RuntimeCall::balances(balances::Call::transfer {
+                to: bob.clone(),
+                amount: ENDOWMENT + 1,
+            }),
+        }],
+    };
+    let (receipts, changeset) = runtime
+        .execute_block_with_changeset(block)
+        .expect("valid block");
+
+    // The nonce of the caller is written before the call fails, and is not rolled back.
+    assert_eq!(receipts[0].result, Err("Not enough funds."));
+    assert_eq!(receipts[0].storage.writes, 1);
+    assert_eq!(changeset.extrinsics[0].len(), 1);
+    assert!(changeset.extrinsics[0].contains_key("system"));
+    assert_eq!(runtime.system.get_nonce(&alice), nonce + 1);
+    assert_eq!(runtime.balances.balance(&alice), ENDOWMENT);
+    assert_eq!(runtime.balances.balance(&bob), ENDOWMENT);
+}
+
+#[test]
+fn different_blocks_hash_differently() {
+    let alice = ACCOUNTS[0].to_string();
+    let bob = ACCOUNTS[1].to_string();
+    let hashes: Vec<_> = [1, 2]
+        .into_iter()
+        .map(|amount| {
+            let mut runtime = genesis();
+            let block = types::Block {
+                header: support::Header { block_number: 2 },
+                extrinsics: vec![support::Extrinsic {
+                    caller: alice.clone(),
+                    call: RuntimeCall::balances(balances::Call::transfer {
+                        to: bob.clone(),
+                        amount,
+                    }),
+                }],
+            };
+            runtime.execute_block(block).expect("valid block");
+            (runtime.system.block_hash(1), runtime.system.block_hash(2))
+        })
+        .collect();
+
+    // Both blocks are at the same height on the same parent, but change the state differently.
+    assert_eq!(hashes[0].0, hashes[1].0);
+    assert!(hashes[0].1.is_some());
+    assert_ne!(hashes[0].1, hashes[1].1);
+}
@@ -1,9 +1,20 @@
+mod assets;
 mod balances;
+mod cli;
+#[cfg(test)]
+mod harness;
 mod proof_of_existence;
 mod support;
 mod system;
+mod vesting;
 
+use crate::support::currency::{
+    BalanceStatus, FreezableCurrency, HoldableCurrency, LockableCurrency, Reasons,
+    ReservableCurrency,
+};
+use crate::support::hashing::{to_hex, Digest};
 use crate::support::Dispatch;
+use proof_of_existence::Claim;
 mod types {
     use crate::support;
 
@@ -11,7 +22,7 @@ mod types {
     pub type Balance = u128;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
-    pub type Content = &'static str;
+    pub type AssetId = u32;
 
     /*Define a concrete `Extrinsic` type using `AccountId` and `RuntimeCall`. */
     pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
@@ -19,21 +30,105 @@ mod types {
     pub type Header = support::Header<BlockNumber>;
     /* Define a concrete `Block` type using `Header` and `Extrinsic`. */
     pub type Block = support::Block<Header, Extrinsic>;
+
+    /* The cost of storage accesses, used to compute the weight of each extrinsic. */
+    pub const DB_WEIGHT: support::storage::DbWeight =
+        support::storage::DbWeight { read: 25, write: 100, per_byte: 1 };
+}
+
+
+// The events of all the pallets of our runtime.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeEvent {
+    balances(balances::Event<types::AccountId, types::Balance>),
+    proof_of_existence(proof_of_existence::Event<types::AccountId>),
+}
+
+impl From<balances::Event<types::AccountId, types::Balance>> for RuntimeEvent {
+    fn from(event: balances::Event<types::AccountId, types::Balance>) -> Self {
+        RuntimeEvent::balances(event)
+    }
+}
+
+impl From<proof_of_existence::Event<types::AccountId>> for RuntimeEvent {
+    fn from(event: proof_of_existence::Event<types::AccountId>) -> Self {
+        RuntimeEvent::proof_of_existence(event)
+    }
 }
 
+// The reasons funds can be held for, by the pallets of our runtime.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeHoldReason {
+    proof_of_existence(proof_of_existence::HoldReason),
+}
+
+impl From<proof_of_existence::HoldReason> for RuntimeHoldReason {
+    fn from(reason: proof_of_existence::HoldReason) -> Self {
+        RuntimeHoldReason::proof_of_existence(reason)
+    }
+}
+
+// The reasons funds can be frozen for, by the pallets of our runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeFreezeReason {
+    Staking,
+}
+
+impl support::storage::Encode for RuntimeHoldReason {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            RuntimeHoldReason::proof_of_existence(reason) => {
+                let mut bytes = vec![0];
+                bytes.extend(reason.encode());
+                bytes
+            }
+        }
+    }
+}
+
+impl support::storage::Encode for RuntimeFreezeReason {
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
 
 impl system::Config for Runtime {
     type AccountId = types::AccountId;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    const PRUNING: support::storage::Pruning = support::storage::Pruning::KeepLast(256);
+    const BLOCK_HASH_COUNT: u64 = 256;
 }
 
 impl proof_of_existence::Config for Runtime {
-    type Content = types::Content;
+    const HASHING: support::hashing::Hashing = support::hashing::Hashing::Blake2b256;
+    type Balance = types::Balance;
+    type Currency = balances::Pallet<Runtime>;
+    type RuntimeHoldReason = RuntimeHoldReason;
+    const CLAIM_DEPOSIT: types::Balance = 3;
+    const MAX_CLAIMS: usize = 16;
+}
+
+impl assets::Config for Runtime {
+    type AssetId = types::AssetId;
+    type AssetBalance = types::Balance;
+}
+
+impl vesting::Config for Runtime {
+    type Balance = types::Balance;
+    type Currency = balances::Pallet<Runtime>;
+    const MAX_SCHEDULES: usize = 3;
+    const MIN_VESTED_TRANSFER: types::Balance = 5;
 }
 
 impl balances::Config for Runtime {
     type Balance = types::Balance;
+    const EXISTENTIAL_DEPOSIT: types::Balance = 5;
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type RuntimeFreezeReason = RuntimeFreezeReason;
 }
 
 
@@ -48,6 +143,62 @@ pub struct Runtime {
     /* create a field `balances` which is of type `balances::Pallet`. */
     balances: balances::Pallet<Self>,
     proof_of_existence: proof_of_existence::Pallet<Self>,
+    assets: assets::Pallet<Self>,
+    vesting: vesting::Pallet<Self>,
+}
+
+// A check of the whole runtime, which fails if an invariant does not hold.
+type Invariant = fn(&Runtime) -> support::DispatchResult;
+
+// The invariants which must hold after every extrinsic, with what they check.
+const INVARIANTS: &[(&str, Invariant)] = &[
+    ("balances add up to the total issuance", |runtime| {
+        runtime.balances.check_invariants()
+    }),
+    ("asset balances add up to their supply", |runtime| {
+        runtime.assets.check_invariants()
+    }),
+    ("claim owners exist", claim_owners_exist),
+];
+
+impl Runtime {
+    // Check all the `INVARIANTS`, returning the first one which does not hold with its error.
+    fn check_invariants(&self) -> Result<(), (&'static str, &'static str)> {
+        for (name, check) in INVARIANTS {
+            check(self).map_err(|e| (*name, e))?;
+        }
+        Ok(())
+    }
+}
+
+// Check that the owners of every claim have accounts, and that no claim outlived its expiry.
+//
+// Owners are kept alive by the consumer reference each of their claims holds on their
+// `system::AccountInfo`, which keeps their account from being reaped.
+fn claim_owners_exist(runtime: &Runtime) -> support::DispatchResult {
+    let block_number = runtime.system.block_number();
+    let mut cursor = None;
+    loop {
+        let page = runtime.proof_of_existence.claims(cursor.as_ref(), 100);
+        if page.items.iter().any(|(_, info)| {
+            !info
+                .owners()
+                .all(|owner| runtime.system.account_exists(owner))
+        }) {
+            return Err("claim owned by an account which does not exist");
+        }
+        if page
+            .items
+            .iter()
+            .any(|(_, info)| info.expires_at.is_some_and(|at| at < block_number))
+        {
+            return Err("claim kept after it expired");
+        }
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(()),
+        }
+    }
 }
 
 // impl Runtime {
@@ -84,34 +235,59 @@ pub struct Runtime {
 // }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     /* Create a mutable variable `runtime`, which is a new instance of `Runtime`. */
     let mut runtime = Runtime::new();
     let alice = "Alice".to_string();
     let bob = "Bob".to_string();
-    let charlie = &"Charlie".to_string();
+    let charlie = "Charlie".to_string();
+
+    let root = "Root".to_string();
 
-    /* Set the balance of `alice` to 100, allowing us to execute other transactions. */
-    runtime.balances.set_balance(&alice, 100);
+    /* Make `root` the account allowed to make root-only calls. */
+    runtime.system.set_root(&root);
+    /* Mint 100 for `alice`, allowing us to execute other transactions. */
+    let mint = balances::Call::mint {
+        to: (alice.clone()),
+        amount: (100),
+    };
     let call = balances::Call::transfer {
         to: (bob),
         amount: (69),
     };
     let call2 = balances::Call::transfer {
-        to: ("Charlie".to_string()),
+        to: (charlie.clone()),
         amount: (30),
     };
     let call3 = balances::Call::transfer {
-        to: ("Charlie".to_string()),
+        to: (charlie),
         amount: (30),
     };
     let call4 = balances::Call::transfer {
         to: ("alex".to_string()),
         amount: (10),
     };
+    /* `alex` is left below the existential deposit, and is reaped. */
+    let call5 = balances::Call::transfer {
+        to: ("Charlie".to_string()),
+        amount: (8),
+    };
 
     let block_1 = types::Block {
         header: support::Header { block_number: 1 },
         extrinsics: vec![
+            support::Extrinsic {
+                caller: root,
+                call: RuntimeCall::balances(mint),
+            },
             support::Extrinsic {
                 caller: alice,
                 call: RuntimeCall::balances(call),
@@ -128,28 +304,48 @@ fn main() {
                 caller: "Alice".to_string(),
                 call: RuntimeCall::balances(call4),
             },
+            support::Extrinsic {
+                caller: "alex".to_string(),
+                call: RuntimeCall::balances(call5),
+            },
         ],
     };
 
-    runtime.execute_block(block_1).expect("invalid block");
+    let receipts = runtime
+        .execute_block_inspecting(block_1, |runtime, receipt| {
+            if let Err((invariant, e)) = runtime.check_invariants() {
+                panic!(
+                    "invariant `{}` broken by extrinsic {}: {}",
+                    invariant, receipt.extrinsic_index, e
+                );
+            }
+        })
+        .expect("invalid block");
+    print_receipts(&receipts);
 
+    /* Claims are made for content, hashed by the pallet, or for a digest hashed beforehand. */
+    let digest = proof_of_existence::Pallet::<Runtime>::hash(b"a document hashed by Alice");
     let claim1 = proof_of_existence::Call::create_claim {
-        claim: "hash of bob: hello! this is for bob",
+        claim: Claim::Content(b"hello! this is for bob".to_vec()),
+        expires_at: None,
     };
     let claim2 = proof_of_existence::Call::create_claim {
-        claim: "hash of alice: hello! this is for Alice",
+        claim: Claim::Content(b"hello! this is for Alice".to_vec()),
+        expires_at: None,
     };
     let claim3 = proof_of_existence::Call::revoke_claim {
-        claim: "No hash for this claim",
+        claim: Claim::Digest(digest),
     };
     let claim4 = proof_of_existence::Call::create_claim {
-        claim: "No hash for this claim",
+        claim: Claim::Digest(digest),
+        expires_at: None,
     };
     let claim5 = proof_of_existence::Call::revoke_claim {
-        claim: "No hash for this claim",
+        claim: Claim::Digest(digest),
     };
     let claim6 = proof_of_existence::Call::create_claim {
-        claim: "hash of alice: hello! this is for Alice",
+        claim: Claim::Content(b"hello! this is for Alice".to_vec()),
+        expires_at: None,
     };
 
     let block_2 = types::Block {
@@ -182,8 +378,335 @@ fn main() {
         ],
     };
 
-    runtime.execute_block(block_2).expect("invalid block");
+    let (receipts, changeset) = runtime
+        .execute_block_with_changeset(block_2)
+        .expect("invalid block");
+    print_receipts(&receipts);
+    print_changeset(&changeset);
+
+    /* `alice` creates an asset and mints some for `bob`, who sends part of it to `charlie`. */
+    let asset_id = 1;
+    let asset_calls = vec![
+        assets::Call::create {
+            id: asset_id,
+            admin: "Alice".to_string(),
+            min_balance: 1,
+        },
+        assets::Call::set_metadata {
+            id: asset_id,
+            name: "Example Token".to_string(),
+            symbol: "EXT".to_string(),
+            decimals: 2,
+        },
+        assets::Call::mint {
+            id: asset_id,
+            beneficiary: "Bob".to_string(),
+            amount: 1000,
+        },
+        assets::Call::freeze {
+            id: asset_id,
+            who: "Bob".to_string(),
+        },
+        assets::Call::thaw {
+            id: asset_id,
+            who: "Bob".to_string(),
+        },
+        assets::Call::burn {
+            id: asset_id,
+            who: "Bob".to_string(),
+            amount: 100,
+        },
+    ];
+    let mut extrinsics: Vec<types::Extrinsic> = asset_calls
+        .into_iter()
+        .map(|call| support::Extrinsic {
+            caller: "Alice".to_string(),
+            call: RuntimeCall::assets(call),
+        })
+        .collect();
+    extrinsics.push(support::Extrinsic {
+        caller: "Bob".to_string(),
+        call: RuntimeCall::assets(assets::Call::transfer {
+            id: asset_id,
+            target: "Charlie".to_string(),
+            amount: 250,
+        }),
+    });
+    /* `alice` lets `bob` spend some of her funds. */
+    extrinsics.push(support::Extrinsic {
+        caller: "Alice".to_string(),
+        call: RuntimeCall::balances(balances::Call::approve_transfer {
+            delegate: "Bob".to_string(),
+            amount: 10,
+        }),
+    });
+    extrinsics.push(support::Extrinsic {
+        caller: "Bob".to_string(),
+        call: RuntimeCall::balances(balances::Call::transfer_approved {
+            owner: "Alice".to_string(),
+            to: "Charlie".to_string(),
+            amount: 6,
+        }),
+    });
+    /* `bob` offers his claim to `charlie`, who accepts it. */
+    let bob_claim = Claim::Content(b"hello! this is for bob".to_vec());
+    extrinsics.push(support::Extrinsic {
+        caller: "Bob".to_string(),
+        call: RuntimeCall::proof_of_existence(proof_of_existence::Call::offer_claim {
+            claim: bob_claim.clone(),
+            to: "Charlie".to_string(),
+        }),
+    });
+    extrinsics.push(support::Extrinsic {
+        caller: "Charlie".to_string(),
+        call: RuntimeCall::proof_of_existence(proof_of_existence::Call::accept_claim {
+            claim: bob_claim,
+        }),
+    });
+    /* `alice` notarizes many documents at once, by claiming the root of their Merkle tree. */
+    let documents: Vec<Digest> = [&b"minutes"[..], b"invoice", b"contract"]
+        .map(proof_of_existence::Pallet::<Runtime>::hash)
+        .to_vec();
+    extrinsics.push(support::Extrinsic {
+        caller: "Alice".to_string(),
+        call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: Claim::Batch(documents.clone()),
+            expires_at: None,
+        }),
+    });
+    /* `alice` claims a note only until the end of block 4. */
+    extrinsics.push(support::Extrinsic {
+        caller: "Alice".to_string(),
+        call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: Claim::Content(b"a note for a day".to_vec()),
+            expires_at: Some(4),
+        }),
+    });
+    let block_3 = types::Block {
+        header: support::Header { block_number: 3 },
+        extrinsics,
+    };
+    let receipts = runtime.execute_block(block_3).expect("invalid block");
+    print_receipts(&receipts);
+    let hashing = <Runtime as proof_of_existence::Config>::HASHING;
+    let proof =
+        support::merkle::prove(hashing, &documents, 2).expect("the document is in the batch");
+    println!(
+        "The contract is claimed by {:?}, with the proof {}",
+        runtime
+            .proof_of_existence
+            .batch_claim(&documents[2], &proof)
+            .map(|info| &info.owner),
+        proof
+    );
+    println!("Events: {:?}", runtime.system.events());
+    println!(
+        "Bob can still spend {} of Alice's funds",
+        runtime
+            .balances
+            .approval(&"Alice".to_string(), &"Bob".to_string())
+    );
+    println!(
+        "Asset {}: {:?} {:?}, Charlie holds {} (frozen: {})",
+        asset_id,
+        runtime.assets.asset(asset_id),
+        runtime.assets.metadata(asset_id),
+        runtime.assets.balance(asset_id, &"Charlie".to_string()),
+        runtime.assets.is_frozen(asset_id, &"Charlie".to_string())
+    );
+
+    /* `charlie` grants `dave` funds unlocking over time, which `dave` merges into one schedule. */
+    let dave = "Dave".to_string();
+    let grants = [(10, 2, 5), (6, 1, 4)].map(|(locked, per_block, starting_block)| {
+        support::Extrinsic {
+            caller: "Charlie".to_string(),
+            call: RuntimeCall::vesting(vesting::Call::vested_transfer {
+                target: dave.clone(),
+                schedule: vesting::VestingInfo {
+                    locked,
+                    per_block,
+                    starting_block,
+                },
+            }),
+        }
+    });
+    let mut extrinsics = Vec::from(grants);
+    extrinsics.push(support::Extrinsic {
+        caller: dave.clone(),
+        call: RuntimeCall::vesting(vesting::Call::merge_schedules {
+            schedule1_index: 0,
+            schedule2_index: 1,
+        }),
+    });
+    let block_4 = types::Block {
+        header: support::Header { block_number: 4 },
+        extrinsics,
+    };
+    let receipts = runtime.execute_block(block_4).expect("invalid block");
+    print_receipts(&receipts);
+    let note = proof_of_existence::Pallet::<Runtime>::hash(b"a note for a day");
+    println!(
+        "Alice's note is still claimed after block 4: {}, its history: {:?}",
+        runtime.proof_of_existence.get_claim(&note).is_some(),
+        runtime.proof_of_existence.history(&note)
+    );
+    println!(
+        "Dave's schedules: {:?}, locked at block 6: {}",
+        runtime.vesting.schedules(&dave),
+        runtime.vesting.locked_at(&dave, 6)
+    );
+
+    println!(
+        "Alice's nonce: {}",
+        runtime.system.get_nonce(&"Alice".to_string())
+    );
+    let bob_claim = runtime
+        .state_at(1, |runtime| {
+            let claim = runtime
+                .proof_of_existence
+                .get_claim(&proof_of_existence::Pallet::<Runtime>::hash(
+                    b"hello! this is for bob",
+                ))
+                .cloned();
+            (runtime.system.get_nonce(&"Bob".to_string()), claim)
+        })
+        .expect("state of block 1 is kept");
+    println!("Bob's nonce and claim at block 1: {:?}", bob_claim);
+
+    // Other pallets move funds through the `Currency` traits, without knowing about `balances`.
+    use_currency::<balances::Pallet<Runtime>>(
+        &mut runtime,
+        &"Bob".to_string(),
+        &"Charlie".to_string(),
+    );
+
+    // List all the balances, a page of two accounts at a time.
+    let mut cursor = None;
+    loop {
+        let page = runtime.balances.balances(cursor.as_ref(), 2);
+        println!("Balances: {:?}", page.items);
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    println!("Nonces: {:?}", runtime.system.nonces(None, 10).items);
+    println!(
+        "Claims: {:?}",
+        hex_claims(runtime.proof_of_existence.claims(None, 10).items)
+    );
+    let alice_claim = proof_of_existence::Pallet::<Runtime>::hash(b"hello! this is for Alice");
+    println!(
+        "Alice's claim {} was made in block {:?}",
+        to_hex(&alice_claim),
+        runtime.proof_of_existence.created_at(&alice_claim)
+    );
+    println!(
+        "Alice's claims: {:?}",
+        hex_claims(
+            runtime
+                .proof_of_existence
+                .claims_of(&"Alice".to_string(), None, 10)
+                .items
+        )
+    );
+    for block_number in 1..=runtime.system.block_number() {
+        let hash = runtime.system.block_hash(block_number);
+        println!(
+            "Block {} hash: {:?}",
+            block_number,
+            hash.map(|hash| to_hex(&hash))
+        );
+    }
+    runtime.check_invariants().expect("invariants hold");
+    let database = cli::default_database();
+    match cli::save_claims(&runtime, &database) {
+        Ok(()) => println!("Claims saved to {}", database.display()),
+        Err(e) => println!("Cannot save the claims to {}: {}", database.display(), e),
+    }
 
     println!("{:?}", runtime)
 }
 
+// Pay, reserve, lock, hold and freeze funds as a pallet depending on a `Currency` in its `Config`
+// would.
+fn use_currency<C>(runtime: &mut Runtime, from: &types::AccountId, to: &types::AccountId)
+where
+    C: ReservableCurrency<Runtime, AccountId = types::AccountId, Balance = types::Balance>
+        + LockableCurrency<Runtime>
+        + HoldableCurrency<Runtime, Reason = RuntimeHoldReason>
+        + FreezableCurrency<Runtime, Id = RuntimeFreezeReason>,
+{
+    C::transfer(runtime, from, to, 4, true).expect("the sender can pay");
+    C::reserve(runtime, to, 10).expect("the recipient can reserve funds");
+    C::set_lock(runtime, *b"example ", to, 20, Reasons::All);
+    println!(
+        "{}'s free and reserved balance: {} {}",
+        to,
+        C::free_balance(runtime, to),
+        C::reserved_balance(runtime, to)
+    );
+    C::extend_lock(runtime, *b"example ", to, 25, Reasons::Transfer);
+    C::remove_lock(runtime, *b"example ", to);
+    C::repatriate_reserved(runtime, to, from, 5, BalanceStatus::Free).expect("funds are reserved");
+    C::slash_reserved(runtime, to, 1);
+    C::unreserve(runtime, to, 4);
+
+    // Holds for a reason cannot be taken by other holds or plain reserves.
+    let deposit = proof_of_existence::HoldReason::ClaimDeposit.into();
+    let held = C::balance_on_hold(runtime, deposit, to);
+    C::hold(runtime, deposit, to, 6).expect("the recipient can hold funds");
+    C::set_freeze(runtime, RuntimeFreezeReason::Staking, to, 10);
+    C::extend_freeze(runtime, RuntimeFreezeReason::Staking, to, 12);
+    println!(
+        "{}'s deposits held and funds frozen: {} {}",
+        to,
+        C::balance_on_hold(runtime, deposit, to),
+        C::balance_frozen(runtime, RuntimeFreezeReason::Staking, to)
+    );
+    C::thaw(runtime, RuntimeFreezeReason::Staking, to);
+    C::release(runtime, deposit, to, 6);
+    assert_eq!(C::balance_on_hold(runtime, deposit, to), held);
+    println!("Total issuance: {}", C::total_issuance(runtime));
+}
+
+// Format the digests of `claims` as hexadecimal with their owner, for printing.
+fn hex_claims(
+    claims: Vec<(Digest, proof_of_existence::ClaimInfoOf<Runtime>)>,
+) -> Vec<(String, types::AccountId)> {
+    claims
+        .into_iter()
+        .map(|(digest, info)| (to_hex(&digest), info.owner))
+        .collect()
+}
+
+// Print the outcome and the storage weight of every extrinsic in a block.
+fn print_receipts(receipts: &[support::Receipt]) {
+    for receipt in receipts {
+        println!(
+            "Extrinsic {}: {:?}, {} reads, {} writes, {} bytes, weight {}",
+            receipt.extrinsic_index,
+            receipt.result,
+            receipt.storage.reads,
+            receipt.storage.writes,
+            receipt.storage.bytes_read + receipt.storage.bytes_written,
+            receipt.storage.weight(types::DB_WEIGHT)
+        );
+    }
+}
+
+// Print every storage change of a block, grouped by extrinsic and pallet.
+fn print_changeset(changeset: &support::storage::Changeset) {
+    for (i, pallets) in changeset.extrinsics.iter().enumerate() {
+        println!("Extrinsic {} changes:", i);
+        for (pallet, changes) in pallets {
+            for change in changes {
+                println!(
+                    "\t{}::{} {:?}: {:?} -> {:?}",
+                    pallet, change.item, change.key, change.old, change.new
+                );
+            }
+        }
+    }
+}
+
@@ -1,14 +1,241 @@
+use crate::support::currency::HoldableCurrency;
+use crate::support::hashing::{Digest, Hashing};
+use crate::support::merkle::{self, MerkleProof};
+use crate::support::storage::{Encode, Page, Prune, StorageMap};
 use crate::support::DispatchResult;
 use core::fmt::Debug;
-use std::collections::BTreeMap;
+use std::path::Path;
 
-pub trait Config: crate::system::Config {
-	/// The type which represents the content that can be claimed using this pallet.
-	/// Could be the content directly as bytes, or better yet the hash of that content.
-	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+pub trait Config:
+	crate::system::Config<RuntimeEvent: From<Event<Self::AccountId>>>
+	+ Sized
+	+ AsMut<crate::system::Pallet<Self>>
+{
+	/// The hash function claimed content is hashed with. Claims are keyed by the digest of their
+	/// content, so it cannot be changed once claims have been made.
+	const HASHING: Hashing;
+	/// The type used to represent an amount of funds.
+	type Balance: Debug + Copy;
+	/// The currency claim deposits are held in.
+	type Currency: HoldableCurrency<
+		Self,
+		AccountId = Self::AccountId,
+		Balance = Self::Balance,
+		Reason = Self::RuntimeHoldReason,
+	>;
+	/// The reasons funds can be held for in the runtime, which include the ones of this pallet.
+	type RuntimeHoldReason: From<HoldReason>;
+	/// The deposit held from the owner of each claim, until the claim is revoked.
+	const CLAIM_DEPOSIT: Self::Balance;
+	/// The maximum number of claims an account can own.
+	const MAX_CLAIMS: usize;
 }
 
+/// The reasons this pallet holds funds for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldReason {
+	/// The deposit of a claim, held from its owner.
+	ClaimDeposit,
+}
+
+impl Encode for HoldReason {
+	fn encode(&self) -> Vec<u8> {
+		vec![*self as u8]
+	}
+}
+
+/// The content a claim is made for: either the content itself, hashed by the pallet, or its
+/// digest, computed beforehand with `Config::HASHING`.
+///
+/// A `Batch` claims the digests of many documents at once, keyed by the root of their Merkle tree.
+/// Each document can then be shown to be included with a `MerkleProof`, see `Pallet::batch_claim`.
+/// The same claim can be made with a `Digest` of the root, computed beforehand with `merkle::root`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+	Content(Vec<u8>),
+	Digest(Digest),
+	Batch(Vec<Digest>),
+}
+
+impl Claim {
+	/// The digest the claim is keyed by.
+	pub fn digest(&self, hashing: Hashing) -> Digest {
+		match self {
+			Claim::Content(content) => hashing.hash(content),
+			Claim::Digest(digest) => *digest,
+			Claim::Batch(documents) => merkle::root(hashing, documents),
+		}
+	}
+}
+
+/// The events deposited by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<AccountId> {
+	/// `owner` claimed the content with the digest `claim`.
+	ClaimCreated { owner: AccountId, claim: Digest },
+	/// `owner` revoked its claim.
+	ClaimRevoked { owner: AccountId, claim: Digest },
+	/// The claim was transferred from `from` to `to`.
+	ClaimTransferred { claim: Digest, from: AccountId, to: AccountId },
+	/// `owner` offered to transfer its claim to `to`, once `to` accepts it.
+	ClaimOffered { claim: Digest, owner: AccountId, to: AccountId },
+	/// `owner` cancelled the offer of its claim.
+	OfferCancelled { claim: Digest, owner: AccountId },
+	/// `owner` changed when its claim expires.
+	ClaimRenewed { owner: AccountId, claim: Digest },
+	/// The claim of `owner` expired, and was removed.
+	ClaimExpired { owner: AccountId, claim: Digest },
+	/// `owner` approved a change of its jointly owned claim, which is made once enough of its
+	/// owners approve it.
+	ChangeApproved { owner: AccountId, claim: Digest },
+}
+
+/// What is known about a claim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimInfo<AccountId, BlockNumber> {
+	/// The account which owns the claim, and whose funds its deposit is held from.
+	pub owner: AccountId,
+	/// The accounts which own the claim along with `owner`, if it is jointly owned.
+	pub co_owners: Vec<AccountId>,
+	/// The number of owners which must approve a change of the claim for it to be made. It is 1
+	/// for a claim with a single owner.
+	pub threshold: u32,
+	/// The block the claim was created in, proving the content existed by then.
+	pub created_at: BlockNumber,
+	/// The block at the end of which the claim is removed, if it expires.
+	pub expires_at: Option<BlockNumber>,
+}
+
+impl<AccountId, BlockNumber> ClaimInfo<AccountId, BlockNumber> {
+	/// All the owners of the claim: `owner`, then the `co_owners`.
+	pub fn owners(&self) -> impl Iterator<Item = &AccountId> {
+		std::iter::once(&self.owner).chain(&self.co_owners)
+	}
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for ClaimInfo<AccountId, BlockNumber> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.owner.encode();
+		bytes.extend(self.co_owners.encode());
+		bytes.extend(self.threshold.encode());
+		bytes.extend(self.created_at.encode());
+		bytes.extend(self.expires_at.encode());
+		bytes
+	}
+}
+
+/// What is known about a claim of the runtime `T`.
+pub type ClaimInfoOf<T> =
+	ClaimInfo<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// A change of a claim, which its owners must approve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimChange<AccountId, BlockNumber> {
+	/// Revoke the claim.
+	Revoke,
+	/// Transfer the claim to `new_owner`, as its only owner.
+	Transfer { new_owner: AccountId },
+	/// Make the claim expire at the end of the block `expires_at`, or never.
+	Renew { expires_at: Option<BlockNumber> },
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for ClaimChange<AccountId, BlockNumber> {
+	fn encode(&self) -> Vec<u8> {
+		match self {
+			ClaimChange::Revoke => vec![0],
+			ClaimChange::Transfer { new_owner } => [vec![1], new_owner.encode()].concat(),
+			ClaimChange::Renew { expires_at } => [vec![2], expires_at.encode()].concat(),
+		}
+	}
+}
+
+/// A change of a jointly owned claim, and the owners which approved it so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approval<AccountId, BlockNumber> {
+	/// The change to make.
+	pub change: ClaimChange<AccountId, BlockNumber>,
+	/// The owners which approved it.
+	pub approvers: Vec<AccountId>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for Approval<AccountId, BlockNumber> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.change.encode();
+		bytes.extend(self.approvers.encode());
+		bytes
+	}
+}
+
+/// A change of a claim of the runtime `T`.
+pub type ClaimChangeOf<T> =
+	ClaimChange<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// A change of a claim of the runtime `T`, and its approvers.
+pub type ApprovalOf<T> =
+	Approval<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// A change in the lifecycle of a claim, made by or happening to the accounts it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimAction<AccountId, BlockNumber> {
+	/// `owner` created the claim, expiring at the end of the block `expires_at` if given.
+	Created { owner: AccountId, expires_at: Option<BlockNumber> },
+	/// The claim was transferred from `from` to `to`.
+	Transferred { from: AccountId, to: AccountId },
+	/// `owner` changed when the claim expires.
+	Renewed { owner: AccountId, expires_at: Option<BlockNumber> },
+	/// `owner` revoked the claim.
+	Revoked { owner: AccountId },
+	/// The claim of `owner` expired.
+	Expired { owner: AccountId },
+	/// `owner` approved `change` of the jointly owned claim.
+	Approved { owner: AccountId, change: ClaimChange<AccountId, BlockNumber> },
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for ClaimAction<AccountId, BlockNumber> {
+	fn encode(&self) -> Vec<u8> {
+		match self {
+			ClaimAction::Created { owner, expires_at } => {
+				[vec![0], owner.encode(), expires_at.encode()].concat()
+			}
+			ClaimAction::Transferred { from, to } => [vec![1], from.encode(), to.encode()].concat(),
+			ClaimAction::Renewed { owner, expires_at } => {
+				[vec![2], owner.encode(), expires_at.encode()].concat()
+			}
+			ClaimAction::Revoked { owner } => [vec![3], owner.encode()].concat(),
+			ClaimAction::Expired { owner } => [vec![4], owner.encode()].concat(),
+			ClaimAction::Approved { owner, change } => {
+				[vec![5], owner.encode(), change.encode()].concat()
+			}
+		}
+	}
+}
+
+/// An entry of the history of a claim: an action, and the block it happened in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimRecord<AccountId, BlockNumber> {
+	/// The block the action happened in.
+	pub block_number: BlockNumber,
+	/// What happened to the claim.
+	pub action: ClaimAction<AccountId, BlockNumber>,
+	/// The owners which approved the action, in the order they did, if it needed their approval:
+	/// all those which asked for the revocation, transfer or renewal of a jointly owned claim, or
+	/// its only owner. Empty otherwise.
+	pub approvers: Vec<AccountId>,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Encode for ClaimRecord<AccountId, BlockNumber> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.block_number.encode();
+		bytes.extend(self.action.encode());
+		bytes.extend(self.approvers.encode());
+		bytes
+	}
+}
+
+/// An entry of the history of a claim of the runtime `T`.
+pub type ClaimRecordOf<T> =
+	ClaimRecord<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
 
 // A public enum which describes the calls we want to expose to the dispatcher.
 // We should expect that the caller of each call will be provided by the dispatcher,
@@ -64,41 +291,177 @@ pub trait Config: crate::system::Config {
 /// It is a simple module that allows accounts to claim existence of some data.
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-	/// A simple storage map from content to the owner of that content.
+	/// A simple storage map from the digest of some content to the owner of that content, and when
+	/// it was claimed.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
-	// Add a field `claims` which is a `BTreeMap` fom `T::Content` to `T::AccountId`. */
-    claims: BTreeMap<T::Content, T::AccountId>
+	// Add a field `claims` which is a `BTreeMap` fom `Digest` to `T::AccountId`. */
+    claims: StorageMap<Digest, ClaimInfoOf<T>>,
+	/// The digests of the claims of each owner, in order, so they can be listed without going
+	/// through all the claims.
+	owned: StorageMap<T::AccountId, Vec<Digest>>,
+	/// The account each claim was offered to by its owner, which can accept it.
+	offers: StorageMap<Digest, T::AccountId>,
+	/// The claims which expire at the end of each block, keyed by block number.
+	expiring: StorageMap<u64, Vec<Digest>>,
+	/// Everything that happened to each claim which was ever made, oldest first. It is kept after
+	/// the claim is removed, so that its lifecycle can be audited.
+	history: StorageMap<Digest, Vec<ClaimRecordOf<T>>>,
+	/// The changes of each jointly owned claim approved by some of its owners, but not enough yet.
+	approvals: StorageMap<Digest, Vec<ApprovalOf<T>>>,
 }
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
 	
-	/// Create a new claim on behalf of the `caller`.
+	/// Create a new claim on behalf of the `caller`, holding the claim deposit from it.
 	/// This function will return an error if someone already has claimed that content.
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		/*Check that a `claim` does not already exist. If so, return an error. */
-		if self.claims.contains_key(&claim) {
-			return Err(&"this content is already claimed");
-		}
-		/* `insert` the claim on behalf of `caller`. */
-		self.claims.insert(claim, caller);
+	/// The `caller` cannot be reaped while it owns the claim. If `expires_at` is given, the claim
+	/// is removed at the end of that block, and the deposit returned.
+	pub fn create_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResult {
+		self.do_create(runtime, caller, claim, Vec::new(), 1, expires_at)
+	}
+
+	/// Create a new claim owned by the `caller` along with the `co_owners`, holding the claim
+	/// deposit from the `caller`. It is revoked, transferred or renewed once `threshold` of its
+	/// owners have called `revoke_claim`, `transfer_claim` or `renew_claim` with the same
+	/// arguments. The co-owners must have accounts, which cannot be reaped while they own the
+	/// claim.
+	pub fn create_joint_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+		co_owners: Vec<T::AccountId>,
+		threshold: u32,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResult {
+		self.do_create(runtime, caller, claim, co_owners, threshold, expires_at)
+	}
+
+
+	pub fn revoke_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		/* Check that the `caller` owns the claim, and that enough of its owners approve. */
+		let Some(approvers) = self.approve(runtime, &caller, claim, ClaimChange::Revoke)? else {
+			return Ok(());
+		};
+		/*If all checks pass, then `remove` the `claim`. */
+		self.remove_claim(runtime, claim);
+		self.record(runtime, claim, ClaimAction::Revoked { owner: caller.clone() }, approvers);
+		deposit_event(runtime, Event::ClaimRevoked { owner: caller, claim });
 		Ok(())
 	}
 
+	/// Change when a claim of the `caller` expires: at the end of the block `expires_at`, or never.
+	pub fn renew_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		ensure_future(runtime, expires_at)?;
+		let change = ClaimChange::Renew { expires_at };
+		let Some(approvers) = self.approve(runtime, &caller, claim, change)? else {
+			return Ok(());
+		};
+		let mut info = self.claims.get(&claim).ok_or("claim does not exist")?.clone();
+		self.unschedule_expiry(&claim, info.expires_at);
+		self.schedule_expiry(claim, expires_at);
+		info.expires_at = expires_at;
+		self.claims.insert(claim, info);
+		self.approvals.remove(&claim);
+		let action = ClaimAction::Renewed { owner: caller.clone(), expires_at };
+		self.record(runtime, claim, action, approvers);
+		deposit_event(runtime, Event::ClaimRenewed { owner: caller, claim });
+		Ok(())
+	}
 
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		/* Get the owner of the `claim` to be revoked. */
-		let caller_state = self.get_claim(&claim).ok_or("claim does not exist")?;
-	
-		
-		/* Check that the `owner` matches the `caller`. */
-		if caller != *caller_state {
-			return Err(&"this content is owned by someone else");
+	/// Transfer a claim of the `caller` to `new_owner`, at once, or once enough of its owners
+	/// approve if it is jointly owned. `new_owner` becomes its only owner, and the claim keeps the
+	/// block it was created in. `new_owner` must have an account, which cannot be reaped while it
+	/// owns the claim. The claim deposit is held from `new_owner`, and the one of the previous
+	/// owner is released.
+	pub fn transfer_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+		new_owner: T::AccountId,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		if !system.account_exists(&new_owner) {
+			return Err("the new owner does not exist");
 		}
-		/*If all checks pass, then `remove` the `claim`. */
-		self.claims.remove(&claim);
+		let change = ClaimChange::Transfer { new_owner: new_owner.clone() };
+		let Some(approvers) = self.approve(runtime, &caller, claim, change)? else {
+			return Ok(());
+		};
+		self.do_transfer(runtime, claim, new_owner, approvers)
+	}
+
+	/// Offer a claim of the `caller` to `to`, who becomes its owner by accepting it. This replaces
+	/// any earlier offer of the claim. Jointly owned claims cannot be offered.
+	pub fn offer_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+		to: T::AccountId,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		self.ensure_owner(&caller, &claim)?;
+		if self.claims.get(&claim).is_some_and(|info| !info.co_owners.is_empty()) {
+			return Err("a jointly owned claim cannot be offered");
+		}
+		self.offers.insert(claim, to.clone());
+		deposit_event(runtime, Event::ClaimOffered { claim, owner: caller, to });
+		Ok(())
+	}
+
+	/// Cancel the offer of a claim of the `caller`.
+	pub fn cancel_offer(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		self.ensure_owner(&caller, &claim)?;
+		if !self.offers.contains_key(&claim) {
+			return Err("no offer to cancel");
+		}
+		self.offers.remove(&claim);
+		deposit_event(runtime, Event::OfferCancelled { claim, owner: caller });
 		Ok(())
 	}
+
+	/// Accept a claim offered to the `caller`, becoming its owner.
+	pub fn accept_claim(
+		&mut self,
+		runtime: &mut T,
+		caller: T::AccountId,
+		claim: Claim,
+	) -> DispatchResult {
+		let claim = claim.digest(T::HASHING);
+		if self.offers.get(&claim) != Some(&caller) {
+			return Err("this claim was not offered to the caller");
+		}
+		self.do_transfer(runtime, claim, caller, Vec::new())
+	}
 }
 
 
@@ -108,36 +471,476 @@ impl<T: Config> Pallet<T> {
 		/* Return a new instance of the `Pallet` struct. */
         
             Self {
-                claims: BTreeMap::<T::Content, T::AccountId>::new(),
+                claims: StorageMap::new("proof_of_existence", "claims"),
+                owned: StorageMap::new("proof_of_existence", "owned"),
+                offers: StorageMap::new("proof_of_existence", "offers"),
+                expiring: StorageMap::new("proof_of_existence", "expiring"),
+                history: StorageMap::new("proof_of_existence", "history"),
+                approvals: StorageMap::new("proof_of_existence", "approvals"),
             }
         
 	}
 
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+	/// Hash `content` into the digest a claim for it is keyed by.
+	pub fn hash(content: &[u8]) -> Digest {
+		T::HASHING.hash(content)
+	}
+
+	/// Hash the content of the file at `path` into the digest a claim for it is keyed by, without
+	/// reading the whole file in memory.
+	pub fn hash_file(path: impl AsRef<Path>) -> std::io::Result<Digest> {
+		T::HASHING.hash_file(path)
+	}
+
+	/// The claim of the Merkle root which `proof` shows the digest `document` is included in, if
+	/// there is one.
+	pub fn batch_claim(&self, document: &Digest, proof: &MerkleProof) -> Option<&ClaimInfoOf<T>> {
+		self.claims.get(&proof.root(T::HASHING, document)?)
+	}
+
+	/// The owner of the claim for the digest `claim`, if any.
+	pub fn get_claim(&self, claim: &Digest) -> Option<&T::AccountId> {
 		//`get` the `claim` */
 		
+		self.claims.get(claim).map(|info| &info.owner)
+	}
+
+	/// The owner of the claim for the digest `claim` and when it was made, if any.
+	pub fn claim_info(&self, claim: &Digest) -> Option<&ClaimInfoOf<T>> {
 		self.claims.get(claim)
 	}
 
+	/// The block the claim for the digest `claim` was created in, if any.
+	pub fn created_at(&self, claim: &Digest) -> Option<T::BlockNumber> {
+		self.claims.get(claim).map(|info| info.created_at)
+	}
+
+	/// The account a claim was offered to, if any.
+	pub fn offer(&self, claim: &Digest) -> Option<&T::AccountId> {
+		self.offers.get(claim)
+	}
+
+	/// The digests of all the claims owned by `owner`, in order.
+	pub fn owned_claims(&self, owner: &T::AccountId) -> Vec<Digest> {
+		self.owned.get(owner).cloned().unwrap_or_default()
+	}
+
+	/// Get up to `limit` claims with what is known about them, in digest order, starting after the
+	/// digest `cursor`.
+	pub fn claims(
+		&self,
+		cursor: Option<&Digest>,
+		limit: usize,
+	) -> Page<Digest, ClaimInfoOf<T>> {
+		self.claims.page(cursor, limit)
+	}
 
+	/// Get up to `limit` claims owned by `owner`, in digest order, starting after the digest
+	/// `cursor`.
+	pub fn claims_of(
+		&self,
+		owner: &T::AccountId,
+		cursor: Option<&Digest>,
+		limit: usize,
+	) -> Page<Digest, ClaimInfoOf<T>> {
+		let owned = self
+			.owned
+			.get(owner)
+			.into_iter()
+			.flatten()
+			.filter(|claim| cursor.is_none_or(|cursor| *claim > cursor))
+			.filter_map(|claim| Some((claim, self.claims.get(claim)?)));
+		Page::new(owned, limit)
+	}
+
+	/// Everything that happened to the claim for the digest `claim`, oldest first, including after
+	/// it was revoked or expired. Empty if it was never made.
+	pub fn history(&self, claim: &Digest) -> &[ClaimRecordOf<T>] {
+		self.history.get(claim).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// Check that `who` owns the claim for the digest `claim`, alone or jointly.
+	fn ensure_owner(&self, who: &T::AccountId, claim: &Digest) -> DispatchResult {
+		/* Get the owners of the `claim`. */
+		let info = self.claims.get(claim).ok_or("claim does not exist")?;
+		if !info.owners().any(|owner| owner == who) {
+			return Err("this content is owned by someone else");
+		}
+		Ok(())
+	}
+
+	/// The changes of the claim for the digest `claim` approved by some of its owners, but not
+	/// enough of them yet.
+	pub fn approvals(&self, claim: &Digest) -> &[ApprovalOf<T>] {
+		self.approvals.get(claim).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// Check that `who` can own one more claim.
+	fn ensure_can_own(&self, who: &T::AccountId) -> DispatchResult {
+		if self.owned.get(who).map_or(0, Vec::len) >= T::MAX_CLAIMS {
+			return Err("the account owns too many claims");
+		}
+		Ok(())
+	}
+
+	/// Create a claim owned by `owner` and the `co_owners`, holding its deposit from `owner`.
+	fn do_create(
+		&mut self,
+		runtime: &mut T,
+		owner: T::AccountId,
+		claim: Claim,
+		mut co_owners: Vec<T::AccountId>,
+		threshold: u32,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResult {
+		if claim == Claim::Batch(Vec::new()) {
+			return Err("a batch must contain some documents");
+		}
+		let claim = claim.digest(T::HASHING);
+		/*Check that a `claim` does not already exist. If so, return an error. */
+		if self.claims.contains_key(&claim) {
+			return Err("this content is already claimed");
+		}
+		let count = co_owners.len();
+		co_owners.sort();
+		co_owners.dedup();
+		if co_owners.len() != count || co_owners.contains(&owner) {
+			return Err("the owners of a claim must be different accounts");
+		}
+		if threshold == 0 || threshold as usize > co_owners.len() + 1 {
+			return Err("the threshold must be between 1 and the number of owners");
+		}
+		ensure_future(runtime, expires_at)?;
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		if !co_owners.iter().all(|co_owner| system.account_exists(co_owner)) {
+			return Err("a co-owner does not exist");
+		}
+		self.ensure_can_own(&owner)?;
+		for co_owner in &co_owners {
+			self.ensure_can_own(co_owner)?;
+		}
+		T::Currency::hold(runtime, HoldReason::ClaimDeposit.into(), &owner, T::CLAIM_DEPOSIT)?;
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		let created_at = system.block_number();
+		let info = ClaimInfo { owner: owner.clone(), co_owners, threshold, created_at, expires_at };
+		/* `insert` the claim on behalf of its owners. */
+		for owner in info.owners() {
+			system.inc_consumers(owner);
+			self.add_owned(owner, claim);
+		}
+		// Log the claim in the block digest, so the hash of the block commits to it.
+		system.deposit_log(claim);
+		self.schedule_expiry(claim, expires_at);
+		self.claims.insert(claim, info);
+		let action = ClaimAction::Created { owner: owner.clone(), expires_at };
+		self.record(runtime, claim, action, Vec::new());
+		deposit_event(runtime, Event::ClaimCreated { owner, claim });
+		Ok(())
+	}
+
+	/// Approve `change` of the claim for the digest `claim` on behalf of `who`, which must own it.
+	/// Returns all the owners which approved it, `who` last, if they are enough for the change to
+	/// be made, in which case the change must clear the approvals of the claim. Otherwise, the
+	/// approval is stored.
+	fn approve(
+		&mut self,
+		runtime: &mut T,
+		who: &T::AccountId,
+		claim: Digest,
+		change: ClaimChangeOf<T>,
+	) -> Result<Option<Vec<T::AccountId>>, &'static str> {
+		self.ensure_owner(who, &claim)?;
+		let threshold = self.claims.get(&claim).map_or(1, |info| info.threshold) as usize;
+		let mut approvals = self.approvals.get(&claim).cloned().unwrap_or_default();
+		let index = match approvals.iter().position(|approval| approval.change == change) {
+			Some(index) => index,
+			None => {
+				approvals.push(Approval { change: change.clone(), approvers: Vec::new() });
+				approvals.len() - 1
+			},
+		};
+		let approvers = &mut approvals[index].approvers;
+		if approvers.contains(who) {
+			return Err("the caller already approved this change");
+		}
+		approvers.push(who.clone());
+		if approvers.len() >= threshold {
+			return Ok(Some(approvers.clone()));
+		}
+		self.approvals.insert(claim, approvals);
+		let action = ClaimAction::Approved { owner: who.clone(), change };
+		self.record(runtime, claim, action, Vec::new());
+		deposit_event(runtime, Event::ChangeApproved { owner: who.clone(), claim });
+		Ok(None)
+	}
+
+	/// Make `to` the only owner of a claim, dropping any offer and approvals of the claim, and
+	/// moving the claim deposit from its owner to `to`. The `approvers` of the transfer are
+	/// recorded in the history of the claim.
+	fn do_transfer(
+		&mut self,
+		runtime: &mut T,
+		claim: Digest,
+		to: T::AccountId,
+		approvers: Vec<T::AccountId>,
+	) -> DispatchResult {
+		let mut info = self.claims.get(&claim).ok_or("claim does not exist")?.clone();
+		if info.owner == to && info.co_owners.is_empty() {
+			return Err("the claim is already owned by this account");
+		}
+		if !info.owners().any(|owner| *owner == to) {
+			self.ensure_can_own(&to)?;
+		}
+		if info.owner != to {
+			T::Currency::hold(runtime, HoldReason::ClaimDeposit.into(), &to, T::CLAIM_DEPOSIT)?;
+			T::Currency::release(
+				runtime,
+				HoldReason::ClaimDeposit.into(),
+				&info.owner,
+				T::CLAIM_DEPOSIT,
+			);
+		}
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		for owner in info.owners() {
+			system.dec_consumers(owner);
+			self.remove_owned(owner, &claim);
+		}
+		system.inc_consumers(&to);
+		self.add_owned(&to, claim);
+		self.offers.remove(&claim);
+		self.approvals.remove(&claim);
+
+		let from = std::mem::replace(&mut info.owner, to.clone());
+		info.co_owners.clear();
+		info.threshold = 1;
+		self.claims.insert(claim, info);
+		let action = ClaimAction::Transferred { from: from.clone(), to: to.clone() };
+		self.record(runtime, claim, action, approvers);
+		deposit_event(runtime, Event::ClaimTransferred { claim, from, to });
+		Ok(())
+	}
+
+	/// Remove the claim for the digest `claim`, with everything depending on it, and return its
+	/// deposit to its owner.
+	fn remove_claim(&mut self, runtime: &mut T, claim: Digest) {
+		let Some(info) = self.claims.get(&claim).cloned() else {
+			return;
+		};
+		self.unschedule_expiry(&claim, info.expires_at);
+		self.claims.remove(&claim);
+		self.offers.remove(&claim);
+		self.approvals.remove(&claim);
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		for owner in info.owners() {
+			system.dec_consumers(owner);
+			self.remove_owned(owner, &claim);
+		}
+		T::Currency::release(
+			runtime,
+			HoldReason::ClaimDeposit.into(),
+			&info.owner,
+			T::CLAIM_DEPOSIT,
+		);
+	}
+
+	/// Add `action` to the history of `claim`, as happening in the current block and approved by
+	/// `approvers`.
+	fn record(
+		&mut self,
+		runtime: &mut T,
+		claim: Digest,
+		action: ClaimAction<T::AccountId, T::BlockNumber>,
+		approvers: Vec<T::AccountId>,
+	) {
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		let record = ClaimRecord { block_number: system.block_number(), action, approvers };
+		let mut history = self.history.get(&claim).cloned().unwrap_or_default();
+		history.push(record);
+		self.history.insert(claim, history);
+	}
+
+	/// Schedule `claim` to be removed at the end of the block `expires_at`, if given.
+	fn schedule_expiry(&mut self, claim: Digest, expires_at: Option<T::BlockNumber>) {
+		if let Some(expires_at) = expires_at {
+			let mut expiring = self.expiring.get(&expires_at.into()).cloned().unwrap_or_default();
+			expiring.push(claim);
+			self.expiring.insert(expires_at.into(), expiring);
+		}
+	}
+
+	/// Cancel the removal of `claim` at the end of the block `expires_at`, if given.
+	fn unschedule_expiry(&mut self, claim: &Digest, expires_at: Option<T::BlockNumber>) {
+		if let Some(expires_at) = expires_at {
+			let mut expiring = self.expiring.get(&expires_at.into()).cloned().unwrap_or_default();
+			expiring.retain(|expiring| expiring != claim);
+			if expiring.is_empty() {
+				self.expiring.remove(&expires_at.into());
+			} else {
+				self.expiring.insert(expires_at.into(), expiring);
+			}
+		}
+	}
+
+	/// Add `claim` to the claims of `owner`, keeping them in order.
+	fn add_owned(&mut self, owner: &T::AccountId, claim: Digest) {
+		let mut owned = self.owned_claims(owner);
+		if let Err(index) = owned.binary_search(&claim) {
+			owned.insert(index, claim);
+		}
+		self.owned.insert(owner.clone(), owned);
+	}
+
+	/// Remove `claim` from the claims of `owner`.
+	fn remove_owned(&mut self, owner: &T::AccountId, claim: &Digest) {
+		let mut owned = self.owned_claims(owner);
+		owned.retain(|owned| owned != claim);
+		if owned.is_empty() {
+			self.owned.remove(owner);
+		} else {
+			self.owned.insert(owner.clone(), owned);
+		}
+	}
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {
+	/// Remove the claims expiring at the end of this block, returning their deposits.
+	fn on_finalize(&mut self, runtime: &mut T) {
+		let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+		let block_number = system.block_number().into();
+		let Some(expiring) = self.expiring.get(&block_number).cloned() else {
+			return;
+		};
+		for claim in expiring {
+			if let Some(owner) = self.get_claim(&claim).cloned() {
+				self.remove_claim(runtime, claim);
+				let action = ClaimAction::Expired { owner: owner.clone() };
+				self.record(runtime, claim, action, Vec::new());
+				deposit_event(runtime, Event::ClaimExpired { owner, claim });
+			}
+		}
+		self.expiring.remove(&block_number);
+	}
+}
+
+impl<T: Config> Prune for Pallet<T> {
+	fn prune(&mut self, earliest: u64) {
+		self.claims.prune(earliest);
+		self.owned.prune(earliest);
+		self.offers.prune(earliest);
+		self.expiring.prune(earliest);
+		self.history.prune(earliest);
+		self.approvals.prune(earliest);
+	}
 }
 
+/// Check that the block `expires_at`, if given, is after the current block.
+fn ensure_future<T: Config>(runtime: &mut T, expires_at: Option<T::BlockNumber>) -> DispatchResult {
+	let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+	match expires_at {
+		Some(expires_at) if expires_at.into() <= system.block_number().into() => {
+			Err("the expiry block must be in the future")
+		},
+		_ => Ok(()),
+	}
+}
+
+/// Deposit an event of this pallet in the `system` pallet of the `runtime`.
+fn deposit_event<T: Config>(runtime: &mut T, event: Event<T::AccountId>) {
+	let system: &mut crate::system::Pallet<T> = runtime.as_mut();
+	system.deposit_event(event);
+}
 
-  
 #[cfg(test)]
 mod test {
-    use super::Pallet;
+	use super::{Claim, ClaimAction, ClaimChange, ClaimInfo, ClaimRecord, Event, HoldReason, Pallet};
+	use crate::balances;
+	use crate::support::hashing::{Digest, Hashing};
+	use crate::support::merkle;
 
-	struct TestConfig;
+	struct TestRuntime {
+		system: crate::system::Pallet<TestRuntime>,
+		balances: balances::Pallet<TestRuntime>,
+	}
 
-	impl super::Config for TestConfig {
-		type Content = &'static str;
+	impl TestRuntime {
+		/// Create a runtime where the accounts making claims have 100 each.
+		fn new() -> Self {
+			let mut runtime =
+				Self { system: crate::system::Pallet::new(), balances: balances::Pallet::new() };
+			let root = "Root".to_string();
+			runtime.system.set_root(&root);
+			let mut balances = core::mem::replace(&mut runtime.balances, balances::Pallet::new());
+			for who in ["Alice", "Bob", "Charles", "Charlie"] {
+				balances.mint(&mut runtime, root.clone(), who.to_string(), 100).unwrap();
+			}
+			runtime.balances = balances;
+			runtime
+		}
+	}
+
+	impl AsMut<crate::system::Pallet<TestRuntime>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut crate::system::Pallet<TestRuntime> {
+			&mut self.system
+		}
+	}
+
+	impl AsRef<balances::Pallet<TestRuntime>> for TestRuntime {
+		fn as_ref(&self) -> &balances::Pallet<TestRuntime> {
+			&self.balances
+		}
+	}
+
+	impl AsMut<balances::Pallet<TestRuntime>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<TestRuntime> {
+			&mut self.balances
+		}
+	}
+
+	impl super::Config for TestRuntime {
+		const HASHING: Hashing = Hashing::Sha256;
+		type Balance = u32;
+		type Currency = balances::Pallet<TestRuntime>;
+		type RuntimeHoldReason = HoldReason;
+		const CLAIM_DEPOSIT: u32 = 10;
+		const MAX_CLAIMS: usize = 3;
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u32;
+		const EXISTENTIAL_DEPOSIT: u32 = 1;
+		type RuntimeHoldReason = HoldReason;
+		type RuntimeFreezeReason = ();
+	}
+
+	#[derive(Debug, Clone, PartialEq)]
+	enum TestEvent {
+		Balances(balances::Event<String, u32>),
+		ProofOfExistence(Event<String>),
+	}
+
+	impl From<balances::Event<String, u32>> for TestEvent {
+		fn from(event: balances::Event<String, u32>) -> Self {
+			TestEvent::Balances(event)
+		}
 	}
 
-	impl crate::system::Config for TestConfig {
+	impl From<Event<String>> for TestEvent {
+		fn from(event: Event<String>) -> Self {
+			TestEvent::ProofOfExistence(event)
+		}
+	}
+
+	impl crate::system::Config for TestRuntime {
 		type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		type RuntimeEvent = TestEvent;
+		const PRUNING: crate::support::storage::Pruning = crate::support::storage::Pruning::Archive;
+		const BLOCK_HASH_COUNT: u64 = 256;
+	}
+
+	fn content(content: &str) -> Claim {
+		Claim::Content(content.as_bytes().to_vec())
 	}
 
 	#[test]
@@ -149,31 +952,573 @@ mod test {
 
 
 
-		let mut poe = Pallet::<TestConfig>::new();
-		assert_eq!(poe.get_claim(&"hash of a private id"), None);
-		let result: Result<(), &str> = poe.create_claim(alice, &"claim");
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"hash of a private id")), None);
+		let result: Result<(), &str> =
+			poe.create_claim(&mut runtime, alice, content("claim"), None);
 		assert_eq!(result, Ok(()));
 
-		let result: Result<(), &str> = poe.create_claim(bob, &"claimBob");
+		let result: Result<(), &str> =
+			poe.create_claim(&mut runtime, bob, content("claimBob"), None);
 		assert_eq!(result, Ok(()));
 
-		let result: Result<(), &str> = poe.create_claim(charles, &"claimBob");
+		let result: Result<(), &str> =
+			poe.create_claim(&mut runtime, charles, content("claimBob"), None);
 		assert_eq!(result, Err("this content is already claimed"));
 
 		let bob = "Bob".to_string();
 
-		let rev = poe.revoke_claim(bob, "claimBob");
+		let rev = poe.revoke_claim(&mut runtime, bob, content("claimBob"));
 		assert_eq!(rev, Ok(()));
 
 		let charles = "Charles".to_string();
 
-		// let rev = std::panic::catch_unwind(|| {
-		// 	poe.revoke_claim(charles, "claimBob")
-		// });
-		
-		
-		// assert_eq!(rev, Some("claim does not exist"));
+		let rev = poe.revoke_claim(&mut runtime, charles, content("claimBob"));
+		assert_eq!(rev, Err("claim does not exist"));
+
+		// Owning a claim, and the deposit held for it, keep the owner from being reaped.
+		assert_eq!(runtime.system.consumers(&"Alice".to_string()), 2);
+		assert_eq!(runtime.system.consumers(&"Bob".to_string()), 0);
+		// The block digest logs every claim created in it, even if revoked since.
+		let created = [
+			Pallet::<TestRuntime>::hash(b"claim"),
+			Pallet::<TestRuntime>::hash(b"claimBob"),
+		];
+		assert_eq!(runtime.system.digest(), &created);
+
+
+	}
+
+	#[test]
+	fn claims_are_keyed_by_digest() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let digest = Hashing::Sha256.hash(b"some content");
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		assert_eq!(
+			poe.create_claim(&mut runtime, alice.clone(), Claim::Digest(digest), None),
+			Ok(())
+		);
+		assert_eq!(poe.get_claim(&digest), Some(&alice));
+
+		// The content and its digest are the same claim.
+		let result = poe.create_claim(&mut runtime, bob.clone(), content("some content"), None);
+		assert_eq!(result, Err("this content is already claimed"));
+		let result = poe.revoke_claim(&mut runtime, bob, Claim::Digest(digest));
+		assert_eq!(result, Err("this content is owned by someone else"));
+
+		let path = std::env::temp_dir().join("proof_of_existence_claims_are_keyed_by_digest");
+		std::fs::write(&path, b"some content").unwrap();
+		let file_digest = Pallet::<TestRuntime>::hash_file(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(file_digest, digest);
+		assert_eq!(poe.revoke_claim(&mut runtime, alice, Claim::Digest(file_digest)), Ok(()));
+		assert_eq!(poe.get_claim(&digest), None);
+	}
 
+	#[test]
+	fn batches_are_claimed_by_their_root() {
+		let alice = "Alice".to_string();
+		let documents: Vec<Digest> = [b"a", b"b", b"c"]
+			.map(|document| Pallet::<TestRuntime>::hash(document))
+			.to_vec();
+		let root = merkle::root(Hashing::Sha256, &documents);
 
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		let result = poe.create_claim(&mut runtime, alice.clone(), Claim::Batch(vec![]), None);
+		assert_eq!(result, Err("a batch must contain some documents"));
+		assert_eq!(
+			poe.create_claim(
+				&mut runtime,
+				alice.clone(),
+				Claim::Batch(documents.clone()),
+				None
+			),
+			Ok(())
+		);
+		assert_eq!(poe.get_claim(&root), Some(&alice));
+
+		// Each document is shown to be claimed with its proof, but not with the one of another.
+		let proof = merkle::prove(Hashing::Sha256, &documents, 1).unwrap();
+		assert_eq!(poe.batch_claim(&documents[1], &proof).map(|info| &info.owner), Some(&alice));
+		assert_eq!(poe.batch_claim(&documents[2], &proof), None);
+
+		// The batch and its root are the same claim.
+		assert_eq!(poe.revoke_claim(&mut runtime, alice, Claim::Digest(root)), Ok(()));
+		assert_eq!(poe.batch_claim(&documents[1], &proof), None);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn claims_are_paginated() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		let mut digests = Vec::new();
+		for (owner, claim) in [(&alice, "a"), (&bob, "b"), (&alice, "c"), (&alice, "d")] {
+			assert_eq!(poe.create_claim(&mut runtime, owner.clone(), content(claim), None), Ok(()));
+			let info = ClaimInfo {
+				owner: owner.clone(),
+				co_owners: vec![],
+				threshold: 1,
+				created_at: 0,
+				expires_at: None,
+			};
+			digests.push((Pallet::<TestRuntime>::hash(claim.as_bytes()), info));
+		}
+		digests.sort_by_key(|(digest, _)| *digest);
+
+		let page = poe.claims(None, 3);
+		assert_eq!(page.items, digests[..3]);
+		assert_eq!(page.next, Some(digests[2].0));
+		let page = poe.claims(page.next.as_ref(), 3);
+		assert_eq!(page.items, digests[3..]);
+		assert_eq!(page.next, None);
+
+		let owned: Vec<_> = digests
+			.iter()
+			.filter(|(_, info)| info.owner == alice)
+			.cloned()
+			.collect();
+		let page = poe.claims_of(&alice, None, 2);
+		assert_eq!(page.items, owned[..2]);
+		assert_eq!(page.next, Some(owned[1].0));
+		let page = poe.claims_of(&alice, page.next.as_ref(), 2);
+		assert_eq!(page.items, owned[2..]);
+		assert_eq!(page.next, None);
+		let info = ClaimInfo {
+			owner: bob.clone(),
+			co_owners: vec![],
+			threshold: 1,
+			created_at: 0,
+			expires_at: None,
+		};
+		assert_eq!(
+			poe.claims_of(&bob, None, 2).items,
+			vec![(Pallet::<TestRuntime>::hash(b"b"), info)]
+		);
+	}
+
+	#[test]
+	fn claims_record_when_they_were_made() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let a = Pallet::<TestRuntime>::hash(b"a");
+		let b = Pallet::<TestRuntime>::hash(b"b");
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		runtime.system.inc_block_number();
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("a"), None), Ok(()));
+		runtime.system.inc_block_number();
+		runtime.system.inc_block_number();
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("b"), None), Ok(()));
+
+		assert_eq!(poe.created_at(&a), Some(1));
+		assert_eq!(
+			poe.claim_info(&b),
+			Some(&ClaimInfo {
+				owner: alice.clone(),
+				co_owners: vec![],
+				threshold: 1,
+				created_at: 3,
+				expires_at: None
+			})
+		);
+		let mut owned = vec![a, b];
+		owned.sort();
+		assert_eq!(poe.owned_claims(&alice), owned);
+
+		// The index of the owner follows the revoked claims.
+		assert_eq!(poe.revoke_claim(&mut runtime, alice.clone(), content("a")), Ok(()));
+		assert_eq!(poe.created_at(&a), None);
+		assert_eq!(poe.owned_claims(&alice), vec![b]);
+		assert_eq!(poe.revoke_claim(&mut runtime, alice.clone(), content("b")), Ok(()));
+		assert!(poe.owned_claims(&alice).is_empty());
+		assert!(poe.owned_claims(&bob).is_empty());
+	}
+
+	#[test]
+	fn claims_can_be_transferred() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let charlie = "Charlie".to_string();
+		let claim = Pallet::<TestRuntime>::hash(b"deed");
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		runtime.system.inc_nonce(&bob);
+		runtime.system.inc_nonce(&charlie);
+		runtime.system.inc_block_number();
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("deed"), None), Ok(()));
+
+		let result =
+			poe.transfer_claim(&mut runtime, bob.clone(), content("deed"), charlie.clone());
+		assert_eq!(result, Err("this content is owned by someone else"));
+		let result = poe.transfer_claim(
+			&mut runtime,
+			alice.clone(),
+			content("deed"),
+			"Dave".to_string(),
+		);
+		assert_eq!(result, Err("the new owner does not exist"));
+		let result = poe.transfer_claim(&mut runtime, alice.clone(), content("other"), bob.clone());
+		assert_eq!(result, Err("claim does not exist"));
+		assert_eq!(
+			poe.transfer_claim(&mut runtime, alice.clone(), content("deed"), bob.clone()),
+			Ok(())
+		);
+		assert_eq!(
+			poe.claim_info(&claim),
+			Some(&ClaimInfo {
+				owner: bob.clone(),
+				co_owners: vec![],
+				threshold: 1,
+				created_at: 1,
+				expires_at: None
+			})
+		);
+		assert!(poe.owned_claims(&alice).is_empty());
+		assert_eq!(poe.owned_claims(&bob), vec![claim]);
+		assert_eq!(runtime.system.consumers(&alice), 0);
+		assert_eq!(runtime.system.consumers(&bob), 2);
+
+		// An offer only moves the claim once accepted by the account it was made to.
+		assert_eq!(
+			poe.offer_claim(&mut runtime, bob.clone(), content("deed"), charlie.clone()),
+			Ok(())
+		);
+		let result = poe.accept_claim(&mut runtime, alice.clone(), content("deed"));
+		assert_eq!(result, Err("this claim was not offered to the caller"));
+		assert_eq!(poe.cancel_offer(&mut runtime, bob.clone(), content("deed")), Ok(()));
+		let result = poe.cancel_offer(&mut runtime, bob.clone(), content("deed"));
+		assert_eq!(result, Err("no offer to cancel"));
+		let result = poe.accept_claim(&mut runtime, charlie.clone(), content("deed"));
+		assert_eq!(result, Err("this claim was not offered to the caller"));
+
+		assert_eq!(
+			poe.offer_claim(&mut runtime, bob.clone(), content("deed"), charlie.clone()),
+			Ok(())
+		);
+		assert_eq!(poe.accept_claim(&mut runtime, charlie.clone(), content("deed")), Ok(()));
+		assert_eq!(poe.get_claim(&claim), Some(&charlie));
+		assert_eq!(poe.offer(&claim), None);
+		assert_eq!(runtime.system.consumers(&bob), 0);
+		assert_eq!(runtime.system.consumers(&charlie), 2);
+
+		assert_eq!(
+			runtime.system.events(),
+			[
+				Event::ClaimCreated { owner: alice.clone(), claim },
+				Event::ClaimTransferred { claim, from: alice, to: bob.clone() },
+				Event::ClaimOffered { claim, owner: bob.clone(), to: charlie.clone() },
+				Event::OfferCancelled { claim, owner: bob.clone() },
+				Event::ClaimOffered { claim, owner: bob.clone(), to: charlie.clone() },
+				Event::ClaimTransferred { claim, from: bob, to: charlie },
+			]
+			.map(TestEvent::from)
+		);
+	}
+
+	#[test]
+	fn claims_hold_deposits() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let dave = "Dave".to_string();
+		let deposit = HoldReason::ClaimDeposit;
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		runtime.system.inc_nonce(&alice);
+		runtime.system.inc_nonce(&bob);
+		runtime.system.inc_nonce(&dave);
+		assert_eq!(
+			poe.create_claim(&mut runtime, dave.clone(), content("a"), None),
+			Err("Not enough funds.")
+		);
+		for claim in ["a", "b", "c"] {
+			assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content(claim), None), Ok(()));
+		}
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 30);
+		assert_eq!(runtime.balances.balance(&alice), 70);
+
+		// The number of claims of an account is capped, however it gets them.
+		let result = poe.create_claim(&mut runtime, alice.clone(), content("d"), None);
+		assert_eq!(result, Err("the account owns too many claims"));
+		assert_eq!(poe.create_claim(&mut runtime, bob.clone(), content("d"), None), Ok(()));
+		let result = poe.transfer_claim(&mut runtime, bob.clone(), content("d"), alice.clone());
+		assert_eq!(result, Err("the account owns too many claims"));
+		let result = poe.transfer_claim(&mut runtime, alice.clone(), content("a"), dave.clone());
+		assert_eq!(result, Err("Not enough funds."));
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"a")), Some(&alice));
+
+		// The deposit moves along with the claim, and is returned when it is revoked.
+		assert_eq!(
+			poe.transfer_claim(&mut runtime, alice.clone(), content("a"), bob.clone()),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 20);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &bob), 20);
+		assert_eq!(poe.revoke_claim(&mut runtime, alice.clone(), content("b")), Ok(()));
+		assert_eq!(poe.revoke_claim(&mut runtime, bob.clone(), content("a")), Ok(()));
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 10);
+		assert_eq!(runtime.balances.balance(&alice), 90);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &bob), 10);
+		assert_eq!(runtime.balances.balance(&bob), 90);
+		assert_eq!(runtime.balances.total_issuance(), 400);
+	}
+
+	#[test]
+	fn claims_expire() {
+		use crate::support::Hooks;
+
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let deposit = HoldReason::ClaimDeposit;
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		runtime.system.inc_nonce(&alice);
+		runtime.system.inc_nonce(&bob);
+		runtime.system.inc_block_number();
+		let result = poe.create_claim(&mut runtime, alice.clone(), content("a"), Some(1));
+		assert_eq!(result, Err("the expiry block must be in the future"));
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("a"), Some(2)), Ok(()));
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("b"), Some(2)), Ok(()));
+		assert_eq!(poe.create_claim(&mut runtime, bob.clone(), content("c"), Some(3)), Ok(()));
+		assert_eq!(poe.claim_info(&Pallet::<TestRuntime>::hash(b"a")).unwrap().expires_at, Some(2));
+
+		// Only the owner can renew a claim, to a later block or forever.
+		let result = poe.renew_claim(&mut runtime, bob.clone(), content("a"), None);
+		assert_eq!(result, Err("this content is owned by someone else"));
+		assert_eq!(poe.renew_claim(&mut runtime, alice.clone(), content("b"), None), Ok(()));
+		assert_eq!(poe.renew_claim(&mut runtime, bob.clone(), content("c"), Some(2)), Ok(()));
+
+		// Nothing expires before the end of the block.
+		poe.on_finalize(&mut runtime);
+		assert_eq!(poe.owned_claims(&alice).len(), 2);
+		runtime.system.inc_block_number();
+		runtime.system.reset_events();
+		poe.on_finalize(&mut runtime);
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"a")), None);
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"b")), Some(&alice));
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"c")), None);
+		assert_eq!(poe.owned_claims(&alice), vec![Pallet::<TestRuntime>::hash(b"b")]);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 10);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &bob), 0);
+		assert_eq!(runtime.system.consumers(&bob), 0);
+		assert!(runtime.system.events().contains(&TestEvent::ProofOfExistence(Event::ClaimExpired {
+			owner: alice.clone(),
+			claim: Pallet::<TestRuntime>::hash(b"a"),
+		})));
+
+		let expired = poe
+			.history(&Pallet::<TestRuntime>::hash(b"a"))
+			.last()
+			.map(|record| &record.action);
+		assert_eq!(expired, Some(&ClaimAction::Expired { owner: alice.clone() }));
+
+		// An expired claim can be made again, by anyone.
+		assert_eq!(poe.create_claim(&mut runtime, bob.clone(), content("a"), None), Ok(()));
+	}
+
+	#[test]
+	fn claims_keep_their_history() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let claim = Pallet::<TestRuntime>::hash(b"deed");
+		let record =
+			|block_number, action, approvers| ClaimRecord { block_number, action, approvers };
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		runtime.system.inc_nonce(&bob);
+		assert_eq!(poe.history(&claim), &[]);
+		runtime.system.inc_block_number();
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("deed"), None), Ok(()));
+		runtime.system.inc_block_number();
+		assert_eq!(
+			poe.transfer_claim(&mut runtime, alice.clone(), content("deed"), bob.clone()),
+			Ok(())
+		);
+		assert_eq!(poe.renew_claim(&mut runtime, bob.clone(), content("deed"), Some(5)), Ok(()));
+		runtime.system.inc_block_number();
+		assert_eq!(
+			poe.revoke_claim(&mut runtime, alice.clone(), content("deed")),
+			Err("this content is owned by someone else")
+		);
+		assert_eq!(poe.revoke_claim(&mut runtime, bob.clone(), content("deed")), Ok(()));
+
+		// The history outlives the claim, and goes on if it is made again.
+		assert_eq!(poe.create_claim(&mut runtime, alice.clone(), content("deed"), None), Ok(()));
+		assert_eq!(
+			poe.history(&claim),
+			&[
+				record(1, ClaimAction::Created { owner: alice.clone(), expires_at: None }, vec![]),
+				record(
+					2,
+					ClaimAction::Transferred { from: alice.clone(), to: bob.clone() },
+					vec![alice.clone()],
+				),
+				record(
+					2,
+					ClaimAction::Renewed { owner: bob.clone(), expires_at: Some(5) },
+					vec![bob.clone()],
+				),
+				record(3, ClaimAction::Revoked { owner: bob.clone() }, vec![bob]),
+				record(3, ClaimAction::Created { owner: alice, expires_at: None }, vec![]),
+			]
+		);
+	}
+
+	#[test]
+	fn joint_claims_need_approvals() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let charles = "Charles".to_string();
+		let charlie = "Charlie".to_string();
+		let deposit = HoldReason::ClaimDeposit;
+		let deed = Pallet::<TestRuntime>::hash(b"deed");
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		for account in [&alice, &bob, &charles, &charlie] {
+			runtime.system.inc_nonce(account);
+		}
+		let owners = vec![bob.clone(), charles.clone()];
+		for (co_owners, threshold, error) in [
+			(owners.clone(), 0, "the threshold must be between 1 and the number of owners"),
+			(owners.clone(), 4, "the threshold must be between 1 and the number of owners"),
+			(vec![bob.clone(), bob.clone()], 2, "the owners of a claim must be different accounts"),
+			(vec![alice.clone()], 1, "the owners of a claim must be different accounts"),
+			(vec!["Dave".to_string()], 1, "a co-owner does not exist"),
+		] {
+			let result = poe.create_joint_claim(
+				&mut runtime,
+				alice.clone(),
+				content("deed"),
+				co_owners,
+				threshold,
+				None,
+			);
+			assert_eq!(result, Err(error));
+		}
+		assert_eq!(
+			poe.create_joint_claim(
+				&mut runtime,
+				alice.clone(),
+				content("deed"),
+				owners,
+				2,
+				None
+			),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 10);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &bob), 0);
+		assert_eq!(runtime.system.consumers(&bob), 1);
+		assert_eq!(poe.owned_claims(&charles), vec![deed]);
+
+		// A change is only made once enough owners ask for the same one.
+		assert_eq!(poe.revoke_claim(&mut runtime, bob.clone(), content("deed")), Ok(()));
+		assert_eq!(poe.get_claim(&deed), Some(&alice));
+		let result = poe.revoke_claim(&mut runtime, bob.clone(), content("deed"));
+		assert_eq!(result, Err("the caller already approved this change"));
+		let result = poe.revoke_claim(&mut runtime, charlie.clone(), content("deed"));
+		assert_eq!(result, Err("this content is owned by someone else"));
+		let result = poe.offer_claim(&mut runtime, alice.clone(), content("deed"), charlie.clone());
+		assert_eq!(result, Err("a jointly owned claim cannot be offered"));
+		assert_eq!(
+			poe.transfer_claim(
+				&mut runtime,
+				charles.clone(),
+				content("deed"),
+				charlie.clone()
+			),
+			Ok(())
+		);
+		assert_eq!(poe.approvals(&deed).len(), 2);
+		let approved = Event::ChangeApproved { owner: charles.clone(), claim: deed };
+		assert!(runtime.system.events().contains(&TestEvent::ProofOfExistence(approved)));
+
+		// The transfer makes the new owner the only one, and drops the other approvals.
+		assert_eq!(
+			poe.transfer_claim(
+				&mut runtime,
+				alice.clone(),
+				content("deed"),
+				charlie.clone()
+			),
+			Ok(())
+		);
+		let info = poe.claim_info(&deed).unwrap();
+		assert_eq!((&info.owner, info.co_owners.len(), info.threshold), (&charlie, 0, 1));
+		assert_eq!(poe.approvals(&deed), &[]);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 0);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &charlie), 10);
+		assert_eq!(runtime.system.consumers(&bob), 0);
+		assert!(poe.owned_claims(&charles).is_empty());
+
+		// A revocation returns the deposit to the owner which made the claim.
+		let result = poe.create_joint_claim(
+			&mut runtime,
+			alice.clone(),
+			content("will"),
+			vec![bob.clone()],
+			2,
+			None,
+		);
+		assert_eq!(result, Ok(()));
+		assert_eq!(poe.revoke_claim(&mut runtime, bob.clone(), content("will")), Ok(()));
+		assert_eq!(poe.revoke_claim(&mut runtime, alice.clone(), content("will")), Ok(()));
+		assert_eq!(poe.get_claim(&Pallet::<TestRuntime>::hash(b"will")), None);
+		assert_eq!(runtime.balances.balance_on_hold(deposit, &alice), 0);
+		assert_eq!(runtime.system.consumers(&bob), 0);
+		let history = poe.history(&Pallet::<TestRuntime>::hash(b"will"));
+		assert_eq!(
+			history[1].action,
+			ClaimAction::Approved {
+				owner: bob,
+				change: ClaimChange::Revoke
+			}
+		);
+		assert_eq!(history[2].action, ClaimAction::Revoked { owner: alice });
+	}
+
+	#[test]
+	fn joint_changes_record_their_approvers() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		let charles = "Charles".to_string();
+		let deed = Pallet::<TestRuntime>::hash(b"deed");
+
+		let mut poe = Pallet::<TestRuntime>::new();
+		let mut runtime = TestRuntime::new();
+		for account in [&alice, &bob, &charles] {
+			runtime.system.inc_nonce(account);
+		}
+		let co_owners = vec![bob.clone(), charles.clone()];
+		let result = poe.create_joint_claim(
+			&mut runtime,
+			alice.clone(),
+			content("deed"),
+			co_owners,
+			2,
+			None,
+		);
+		assert_eq!(result, Ok(()));
+		assert_eq!(poe.revoke_claim(&mut runtime, charles.clone(), content("deed")), Ok(()));
+		assert_eq!(poe.revoke_claim(&mut runtime, alice.clone(), content("deed")), Ok(()));
+		assert_eq!(poe.get_claim(&deed), None);
+
+		// The revocation is recorded with both owners which asked for it, but not the third one.
+		let revoked = poe.history(&deed).last().unwrap();
+		assert_eq!(revoked.action, ClaimAction::Revoked { owner: alice.clone() });
+		assert_eq!(revoked.approvers, vec![charles, alice]);
+	}
+}
@@ -1,3 +1,8 @@
+pub mod currency;
+pub mod hashing;
+pub mod merkle;
+pub mod storage;
+
 /// The most primitive representation of a Blockchain block.
 pub struct Block<Header, Extrinsic> {
     /// The block header contains metadata about the block.
@@ -28,6 +33,17 @@ pub struct Extrinsic<Caller, Call> {
 /// otherwise return a static error message.
 pub type DispatchResult = Result<(), &'static str>;
 
+/// The receipt of a single extrinsic, produced while executing a block.
+#[derive(Debug)]
+pub struct Receipt {
+    /// The position of the extrinsic in its block.
+    pub extrinsic_index: usize,
+    /// The outcome of dispatching the extrinsic.
+    pub result: DispatchResult,
+    /// The storage reads and writes made while dispatching the extrinsic.
+    pub storage: storage::StorageMeter,
+}
+
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
@@ -40,3 +56,29 @@ pub trait Dispatch {
     /// based on the outcome of that function call.
     fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// Functions called by the runtime on each of its pallets while executing a block, besides the
+/// calls of its extrinsics.
+pub trait Hooks<Runtime> {
+    /// Called at the end of every block, after all its extrinsics, with the rest of the runtime.
+    fn on_finalize(&mut self, _runtime: &mut Runtime) {}
+}
+
+/// The same as `Dispatch`, for a pallet which is part of `Runtime`.
+///
+/// The pallet is given the rest of the runtime, so its calls can access the other pallets.
+pub trait PalletDispatch<Runtime> {
+    /// The type used to identify the caller of the function.
+    type Caller;
+    /// The state transition function call the caller is trying to access.
+    type Call;
+
+    /// This function takes the `runtime`, a `caller` and the `call` they want to make, and returns
+    /// a `Result` based on the outcome of that function call.
+    fn dispatch(
+        &mut self,
+        runtime: &mut Runtime,
+        caller: Self::Caller,
+        call: Self::Call,
+    ) -> DispatchResult;
+}
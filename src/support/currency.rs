@@ -0,0 +1,232 @@
+//! Traits for moving funds around, so pallets can charge or pay accounts without knowing which
+//! pallet holds the funds.
+//!
+//! The traits are generic over the `Runtime`, as moving funds may need other pallets than the one
+//! holding them, e.g. to reap an account. A pallet depends on them through its `Config`:
+//!
+//! ```ignore
+//! pub trait Config: crate::system::Config + Sized {
+//!     type Currency: ReservableCurrency<Self, AccountId = Self::AccountId>;
+//! }
+//! ```
+
+use crate::support::storage::Encode;
+use crate::support::DispatchResult;
+
+/// The identifier of a lock, naming what the funds are locked for.
+pub type LockIdentifier = [u8; 8];
+
+/// The kinds of withdrawal a lock prevents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reasons {
+    /// The locked funds cannot be transferred.
+    Transfer,
+    /// The locked funds cannot be reserved.
+    Reserve,
+    /// The locked funds cannot be withdrawn in any way.
+    All,
+}
+
+impl Reasons {
+    /// Whether a lock for these reasons prevents a withdrawal for `reason`.
+    pub fn includes(&self, reason: Reasons) -> bool {
+        *self == Reasons::All || *self == reason
+    }
+}
+
+impl Encode for Reasons {
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+/// Where funds moved out of a reserved balance end up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStatus {
+    /// The funds are added to the free balance.
+    Free,
+    /// The funds are added to the reserved balance.
+    Reserved,
+}
+
+/// Funds held by accounts of the `Runtime`.
+pub trait Currency<Runtime> {
+    /// The type used to identify an account.
+    type AccountId;
+    /// The type used to represent an amount of funds.
+    type Balance;
+
+    /// The total amount of funds in existence.
+    fn total_issuance(runtime: &Runtime) -> Self::Balance;
+
+    /// The funds of `who` which are not reserved.
+    fn free_balance(runtime: &Runtime, who: &Self::AccountId) -> Self::Balance;
+
+    /// Transfer `amount` of the free balance of `from` to `to`.
+    /// `from` may be reaped, unless `keep_alive` is set, in which case the transfer fails instead.
+    fn transfer(
+        runtime: &mut Runtime,
+        from: &Self::AccountId,
+        to: &Self::AccountId,
+        amount: Self::Balance,
+        keep_alive: bool,
+    ) -> DispatchResult;
+}
+
+/// Funds which can be set aside, still owned by their account but not usable.
+pub trait ReservableCurrency<Runtime>: Currency<Runtime> {
+    /// The funds of `who` which are reserved.
+    fn reserved_balance(runtime: &Runtime, who: &Self::AccountId) -> Self::Balance;
+
+    /// Move `amount` from the free balance of `who` to its reserved balance.
+    fn reserve(
+        runtime: &mut Runtime,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> DispatchResult;
+
+    /// Move up to `amount` from the reserved balance of `who` back to its free balance.
+    /// Returns the part of `amount` which could not be unreserved.
+    fn unreserve(
+        runtime: &mut Runtime,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> Self::Balance;
+
+    /// Destroy up to `amount` from the reserved balance of `who`.
+    /// Returns the part of `amount` which could not be slashed.
+    fn slash_reserved(
+        runtime: &mut Runtime,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> Self::Balance;
+
+    /// Move up to `amount` from the reserved balance of `slashed` to `beneficiary`, as `status`
+    /// says. Returns the part of `amount` which could not be moved.
+    fn repatriate_reserved(
+        runtime: &mut Runtime,
+        slashed: &Self::AccountId,
+        beneficiary: &Self::AccountId,
+        amount: Self::Balance,
+        status: BalanceStatus,
+    ) -> Result<Self::Balance, &'static str>;
+}
+
+/// Funds which can be locked, staying in the free balance but not withdrawable.
+pub trait LockableCurrency<Runtime>: Currency<Runtime> {
+    /// Lock `amount` of the free balance of `who` for `reasons`, under the name `id`, replacing any
+    /// lock of the same name.
+    fn set_lock(
+        runtime: &mut Runtime,
+        id: LockIdentifier,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+        reasons: Reasons,
+    );
+
+    /// Extend the lock named `id` on the free balance of `who`, so it locks at least `amount` for
+    /// at least `reasons`.
+    fn extend_lock(
+        runtime: &mut Runtime,
+        id: LockIdentifier,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+        reasons: Reasons,
+    );
+
+    /// Remove the lock named `id` from the free balance of `who`.
+    fn remove_lock(runtime: &mut Runtime, id: LockIdentifier, who: &Self::AccountId);
+}
+
+/// Funds which can be held for a reason, as part of the reserved balance, so that holds for
+/// different reasons cannot take from each other.
+pub trait HoldableCurrency<Runtime>: ReservableCurrency<Runtime> {
+    /// The reasons funds can be held for.
+    type Reason;
+    /// The number of a block, at which held funds can be released.
+    type BlockNumber;
+
+    /// The funds of `who` held for `reason`.
+    fn balance_on_hold(
+        runtime: &Runtime,
+        reason: Self::Reason,
+        who: &Self::AccountId,
+    ) -> Self::Balance;
+
+    /// Move `amount` from the free balance of `who` to its balance held for `reason`.
+    fn hold(
+        runtime: &mut Runtime,
+        reason: Self::Reason,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> DispatchResult;
+
+    /// Move up to `amount` from the balance of `who` held for `reason` back to its free balance.
+    /// Returns the part of `amount` which could not be released.
+    fn release(
+        runtime: &mut Runtime,
+        reason: Self::Reason,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> Self::Balance;
+
+    /// Release up to `amount` from the balance of `who` held for `reason` at the end of the block
+    /// `at`, which must be in the future. `amount` must be held when the release is scheduled.
+    fn release_at(
+        runtime: &mut Runtime,
+        reason: Self::Reason,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+        at: Self::BlockNumber,
+    ) -> DispatchResult;
+
+    /// Destroy up to `amount` from the balance of `who` held for `reason`.
+    /// Returns the part of `amount` which could not be burned.
+    fn burn_held(
+        runtime: &mut Runtime,
+        reason: Self::Reason,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    ) -> Self::Balance;
+
+    /// Move up to `amount` from the balance of `source` held for `reason` to `dest`, either to its
+    /// free balance or held for the same `reason`, as `status` says. Returns the part of `amount`
+    /// which could not be moved.
+    fn transfer_on_hold(
+        runtime: &mut Runtime,
+        reason: Self::Reason,
+        source: &Self::AccountId,
+        dest: &Self::AccountId,
+        amount: Self::Balance,
+        status: BalanceStatus,
+    ) -> Result<Self::Balance, &'static str>;
+}
+
+/// Funds which can be frozen for a reason, staying in the free balance but not withdrawable in any
+/// way.
+pub trait FreezableCurrency<Runtime>: Currency<Runtime> {
+    /// The reasons funds can be frozen for.
+    type Id;
+
+    /// The funds of `who` frozen for `id`.
+    fn balance_frozen(runtime: &Runtime, id: Self::Id, who: &Self::AccountId) -> Self::Balance;
+
+    /// Freeze `amount` of the free balance of `who` for `id`, replacing any freeze for `id`.
+    fn set_freeze(
+        runtime: &mut Runtime,
+        id: Self::Id,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    );
+
+    /// Extend the freeze for `id` on the free balance of `who`, so it freezes at least `amount`.
+    fn extend_freeze(
+        runtime: &mut Runtime,
+        id: Self::Id,
+        who: &Self::AccountId,
+        amount: Self::Balance,
+    );
+
+    /// Remove the freeze for `id` from the free balance of `who`.
+    fn thaw(runtime: &mut Runtime, id: Self::Id, who: &Self::AccountId);
+}
@@ -0,0 +1,101 @@
+//! Hashing of content into fixed size digests, so that it can be referred to on chain without
+//! storing the content itself.
+
+use blake2::digest::consts::U32;
+use blake2::Blake2b;
+use sha2::{Digest as _, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+/// The 32-byte digest of some content.
+pub type Digest = [u8; 32];
+
+/// The hash functions content can be hashed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hashing {
+    /// BLAKE2b, with a 256-bit output.
+    Blake2b256,
+    /// SHA-256.
+    Sha256,
+}
+
+impl Hashing {
+    /// Hash `bytes`.
+    pub fn hash(&self, bytes: &[u8]) -> Digest {
+        match self {
+            Hashing::Blake2b256 => Blake2b::<U32>::digest(bytes).into(),
+            Hashing::Sha256 => Sha256::digest(bytes).into(),
+        }
+    }
+
+    /// Hash the content of the file at `path`, reading it a chunk at a time.
+    pub fn hash_file(&self, path: impl AsRef<Path>) -> std::io::Result<Digest> {
+        let file = std::fs::File::open(path)?;
+        match self {
+            Hashing::Blake2b256 => hash_reader(Blake2b::<U32>::new(), file),
+            Hashing::Sha256 => hash_reader(Sha256::new(), file),
+        }
+    }
+}
+
+/// Feed everything `reader` yields to `hasher`, and return the digest.
+fn hash_reader<H: sha2::Digest>(mut hasher: H, mut reader: impl Read) -> std::io::Result<Digest>
+where
+    Digest: From<sha2::digest::Output<H>>,
+{
+    let mut buffer = [0; 8192];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(hasher.finalize().into()),
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+}
+
+/// Format `digest` as lowercase hexadecimal.
+pub fn to_hex(digest: &Digest) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parse a digest formatted with `to_hex`, if `hex` is one.
+pub fn from_hex(hex: &str) -> Option<Digest> {
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut digest = [0; 32];
+    for (byte, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(
+            to_hex(&Hashing::Sha256.hash(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            to_hex(&Hashing::Blake2b256.hash(b"")),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        let digest = Hashing::Sha256.hash(b"abc");
+        assert_eq!(from_hex(&to_hex(&digest)), Some(digest));
+        assert_eq!(from_hex("ba78"), None);
+    }
+
+    #[test]
+    fn files_hash_like_their_content() {
+        let path = std::env::temp_dir().join("hashing_files_hash_like_their_content");
+        let content = vec![7u8; 20_000];
+        std::fs::write(&path, &content).unwrap();
+        for hashing in [Hashing::Blake2b256, Hashing::Sha256] {
+            assert_eq!(hashing.hash_file(&path).unwrap(), hashing.hash(&content));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! Merkle trees over digests, so that many documents can be claimed at once by claiming the root
+//! of their tree, and any one of them later shown to be included with a short proof.
+//!
+//! Leaves and inner nodes are hashed with different prefixes, so that a node can never be passed
+//! off as a leaf. The last node of a level with an odd number of nodes is moved up to the next
+//! level as it is, rather than paired with itself.
+
+use super::hashing::{from_hex, to_hex, Digest, Hashing};
+use std::fmt;
+use std::str::FromStr;
+
+/// The proof that a leaf is included in a tree, made of the siblings of the nodes on its path to
+/// the root, from the bottom up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// The position of the leaf in the tree.
+    pub index: usize,
+    /// The number of leaves in the tree.
+    pub leaves: usize,
+    /// The siblings of the nodes on the path of the leaf, skipping the levels where it has none.
+    pub siblings: Vec<Digest>,
+}
+
+impl MerkleProof {
+    /// The root of the tree `leaf` is in according to this proof, or `None` if the proof is
+    /// malformed.
+    pub fn root(&self, hashing: Hashing, leaf: &Digest) -> Option<Digest> {
+        if self.index >= self.leaves {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = hash_leaf(hashing, leaf);
+        let (mut index, mut nodes) = (self.index, self.leaves);
+        while nodes > 1 {
+            if index ^ 1 < nodes {
+                let sibling = siblings.next()?;
+                hash = match index % 2 {
+                    0 => hash_node(hashing, &hash, sibling),
+                    _ => hash_node(hashing, sibling, &hash),
+                };
+            }
+            index /= 2;
+            nodes = nodes.div_ceil(2);
+        }
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(hash),
+        }
+    }
+}
+
+/// Formatted as `index/leaves:sibling,sibling,...`, with the siblings in hexadecimal.
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let siblings: Vec<String> = self.siblings.iter().map(to_hex).collect();
+        write!(f, "{}/{}:{}", self.index, self.leaves, siblings.join(","))
+    }
+}
+
+impl FromStr for MerkleProof {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = "malformed merkle proof";
+        let (position, siblings) = s.split_once(':').ok_or(malformed)?;
+        let (index, leaves) = position.split_once('/').ok_or(malformed)?;
+        let siblings = match siblings {
+            "" => Vec::new(),
+            siblings => siblings
+                .split(',')
+                .map(|sibling| from_hex(sibling).ok_or(malformed))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(MerkleProof {
+            index: index.parse().map_err(|_| malformed)?,
+            leaves: leaves.parse().map_err(|_| malformed)?,
+            siblings,
+        })
+    }
+}
+
+/// The root of the tree with `leaves`. The root of no leaves is the hash of no bytes.
+pub fn root(hashing: Hashing, leaves: &[Digest]) -> Digest {
+    if leaves.is_empty() {
+        return hashing.hash(&[]);
+    }
+    let mut level: Vec<Digest> = leaves.iter().map(|leaf| hash_leaf(hashing, leaf)).collect();
+    while level.len() > 1 {
+        level = next_level(hashing, &level);
+    }
+    level[0]
+}
+
+/// The proof that the leaf at `index` is included in the tree with `leaves`, if there is one.
+pub fn prove(hashing: Hashing, leaves: &[Digest], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut level: Vec<Digest> = leaves.iter().map(|leaf| hash_leaf(hashing, leaf)).collect();
+    let mut siblings = Vec::new();
+    let mut position = index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(position ^ 1) {
+            siblings.push(*sibling);
+        }
+        level = next_level(hashing, &level);
+        position /= 2;
+    }
+    Some(MerkleProof {
+        index,
+        leaves: leaves.len(),
+        siblings,
+    })
+}
+
+/// Check that `proof` shows `leaf` to be included in the tree with the root `root`.
+pub fn verify(hashing: Hashing, root: &Digest, leaf: &Digest, proof: &MerkleProof) -> bool {
+    proof.root(hashing, leaf).as_ref() == Some(root)
+}
+
+fn hash_leaf(hashing: Hashing, leaf: &Digest) -> Digest {
+    let mut bytes = vec![0];
+    bytes.extend(leaf);
+    hashing.hash(&bytes)
+}
+
+fn hash_node(hashing: Hashing, left: &Digest, right: &Digest) -> Digest {
+    let mut bytes = vec![1];
+    bytes.extend(left);
+    bytes.extend(right);
+    hashing.hash(&bytes)
+}
+
+/// Hash the nodes of `level` in pairs, moving the last one up as it is if it has no pair.
+fn next_level(hashing: Hashing, level: &[Digest]) -> Vec<Digest> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(hashing, left, right),
+            [node] => *node,
+            _ => unreachable!("chunks have one or two nodes"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<Digest> {
+        (0..count).map(|i| Hashing::Sha256.hash(&[i])).collect()
+    }
+
+    #[test]
+    fn every_leaf_is_proven_included() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = root(Hashing::Sha256, &leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = prove(Hashing::Sha256, &leaves, index).unwrap();
+                assert!(verify(Hashing::Sha256, &root, leaf, &proof));
+                assert_eq!(proof.to_string().parse(), Ok(proof.clone()));
+                // The proof is for this leaf, at this position, in this tree only.
+                let other = Hashing::Sha256.hash(b"other");
+                assert!(!verify(Hashing::Sha256, &root, &other, &proof));
+                let moved = MerkleProof {
+                    index: (index + 1) % leaves.len(),
+                    ..proof.clone()
+                };
+                assert!(count == 1 || !verify(Hashing::Sha256, &root, leaf, &moved));
+                assert!(!verify(Hashing::Blake2b256, &root, leaf, &proof));
+            }
+        }
+        assert_eq!(prove(Hashing::Sha256, &leaves(3), 3), None);
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let leaves = leaves(5);
+        let mut proof = prove(Hashing::Sha256, &leaves, 4).unwrap();
+        proof.siblings.push(leaves[0]);
+        assert_eq!(proof.root(Hashing::Sha256, &leaves[4]), None);
+        proof.siblings.clear();
+        assert_eq!(proof.root(Hashing::Sha256, &leaves[4]), None);
+        let proof = MerkleProof {
+            index: 5,
+            leaves: 5,
+            siblings: vec![],
+        };
+        assert_eq!(proof.root(Hashing::Sha256, &leaves[4]), None);
+        assert_eq!(
+            "1/2:zz".parse::<MerkleProof>(),
+            Err("malformed merkle proof")
+        );
+        assert_eq!(
+            "0/1:".parse(),
+            Ok(MerkleProof {
+                index: 0,
+                leaves: 1,
+                siblings: vec![]
+            })
+        );
+    }
+}
@@ -0,0 +1,745 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::ops::Bound;
+
+/// A very small encoding, used to measure how many bytes a storage access touches.
+///
+/// Integers are encoded as little endian bytes, and strings and vectors are prefixed with their
+/// length as a `u32`.
+pub trait Encode {
+    /// Encode `self` into a new vector of bytes.
+    fn encode(&self) -> Vec<u8>;
+
+    /// The number of bytes `self` takes once encoded.
+    fn encoded_size(&self) -> usize {
+        self.encode().len()
+    }
+}
+
+macro_rules! impl_encode_for_int {
+    ($($int:ty),*) => {
+        $(
+            impl Encode for $int {
+                fn encode(&self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_encode_for_int!(u8, u16, u32, u64, u128);
+
+impl Encode for str {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = (self.len() as u32).encode();
+        bytes.extend_from_slice(self.as_bytes());
+        bytes
+    }
+}
+
+impl Encode for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_str().encode()
+    }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = (self.len() as u32).encode();
+        for item in self {
+            bytes.extend(item.encode());
+        }
+        bytes
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.0.encode();
+        bytes.extend(self.1.encode());
+        bytes
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Some(value) => {
+                let mut bytes = vec![1];
+                bytes.extend(value.encode());
+                bytes
+            }
+            None => vec![0],
+        }
+    }
+}
+
+impl Encode for () {
+    fn encode(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self) -> Vec<u8> {
+        (**self).encode()
+    }
+}
+
+/// The storage accesses made while executing some piece of code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StorageMeter {
+    /// The number of storage reads.
+    pub reads: u32,
+    /// The number of storage writes, including removals.
+    pub writes: u32,
+    /// The number of encoded key and value bytes read from storage.
+    pub bytes_read: u64,
+    /// The number of encoded key and value bytes written to storage.
+    pub bytes_written: u64,
+}
+
+/// The cost of each kind of storage access, used to turn a `StorageMeter` into a weight.
+#[derive(Debug, Clone, Copy)]
+pub struct DbWeight {
+    /// The weight of a single storage read.
+    pub read: u64,
+    /// The weight of a single storage write.
+    pub write: u64,
+    /// The weight of every byte read or written.
+    pub per_byte: u64,
+}
+
+impl StorageMeter {
+    /// The weight of all the storage accesses recorded by this meter.
+    pub fn weight(&self, db: DbWeight) -> u64 {
+        let bytes = self.bytes_read + self.bytes_written;
+        db.read * u64::from(self.reads) + db.write * u64::from(self.writes) + db.per_byte * bytes
+    }
+}
+
+/// A single storage key written or removed, with its encoded value before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange {
+    /// The name of the storage item within its pallet.
+    pub item: &'static str,
+    /// The encoded key.
+    pub key: Vec<u8>,
+    /// The encoded value before the change, `None` if the key did not exist.
+    pub old: Option<Vec<u8>>,
+    /// The encoded value after the change, `None` if the key was removed.
+    pub new: Option<Vec<u8>>,
+}
+
+/// Storage changes grouped by the name of the pallet which made them.
+pub type PalletChanges = BTreeMap<&'static str, Vec<StorageChange>>;
+
+/// All the storage changes made while executing a block.
+#[derive(Debug, Default)]
+pub struct Changeset {
+    /// The changes made by each extrinsic, in the order of the extrinsics in the block.
+    pub extrinsics: Vec<PalletChanges>,
+}
+
+/// A page of storage entries, and the cursor to get the next page with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Page<K, V> {
+    /// The entries of this page, in key order.
+    pub items: Vec<(K, V)>,
+    /// The key of the last entry of this page, if there are more entries after it.
+    pub next: Option<K>,
+}
+
+impl<K: Clone, V: Clone> Page<K, V> {
+    /// Take up to `limit` entries from `entries`.
+    pub fn new<'a>(entries: impl Iterator<Item = (&'a K, &'a V)>, limit: usize) -> Self
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let mut entries = entries.peekable();
+        let items: Vec<(K, V)> = entries
+            .by_ref()
+            .take(limit)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let next = match (items.last(), entries.peek()) {
+            (Some((key, _)), Some(_)) => Some(key.clone()),
+            _ => None,
+        };
+        Page { items, next }
+    }
+}
+
+/// How much of the history of storage is kept, to query the state as of an earlier block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pruning {
+    /// Keep the state as of every block.
+    Archive,
+    /// Keep the state as of the given number of blocks before the latest one.
+    KeepLast(u64),
+}
+
+impl Pruning {
+    /// The earliest block whose state is kept, once `latest` has been executed.
+    pub fn earliest(&self, latest: u64) -> u64 {
+        match self {
+            Pruning::Archive => 0,
+            Pruning::KeepLast(n) => latest.saturating_sub(*n),
+        }
+    }
+}
+
+// A change being recorded, identified by pallet, item and encoded key.
+type ChangeKey = (&'static str, &'static str, Vec<u8>);
+
+thread_local! {
+    // The meter of the code currently running in `metered`, if any.
+    static METER: RefCell<Option<StorageMeter>> = const { RefCell::new(None) };
+    // The changes made by the code currently running in `recorded`, if any.
+    static CHANGES: RefCell<Option<BTreeMap<ChangeKey, StorageChange>>> =
+        const { RefCell::new(None) };
+    // The block currently being executed, if any.
+    static BLOCK: Cell<Option<u64>> = const { Cell::new(None) };
+    // The block whose state is read by the code currently running in `read_at`, if any.
+    static READ_AT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Attribute all following storage writes to block `number`, until `end_block` is called.
+pub fn begin_block(number: u64) {
+    BLOCK.with(|block| block.set(Some(number)));
+}
+
+/// Stop attributing storage writes to the block begun last.
+///
+/// Writes made outside of a block, e.g. at genesis, keep no history: they change the current
+/// state as if it had always held their values.
+pub fn end_block() {
+    BLOCK.with(|block| block.set(None));
+}
+
+/// Storage whose history can be pruned, e.g. a pallet pruning each of its storage items.
+pub trait Prune {
+    /// Forget the values which are only needed to read the state as of blocks before `earliest`.
+    fn prune(&mut self, earliest: u64);
+}
+
+/// Run `f`, with all storage reads returning the state as it was at the end of block `number`.
+///
+/// It is up to the caller to check the state of that block has not been pruned yet.
+pub fn read_at<R>(number: u64, f: impl FnOnce() -> R) -> R {
+    let outer = READ_AT.with(|read_at| read_at.replace(Some(number)));
+    let result = f();
+    READ_AT.with(|read_at| read_at.set(outer));
+    result
+}
+
+/// Run `f`, and return its result together with all the storage accesses it made.
+pub fn metered<R>(f: impl FnOnce() -> R) -> (R, StorageMeter) {
+    let outer = METER.with(|meter| meter.replace(Some(StorageMeter::default())));
+    let result = f();
+    let inner = METER.with(|meter| meter.replace(outer)).unwrap_or_default();
+    // Accesses made by nested code are also accesses made by the outer code.
+    note(|meter| {
+        meter.reads += inner.reads;
+        meter.writes += inner.writes;
+        meter.bytes_read += inner.bytes_read;
+        meter.bytes_written += inner.bytes_written;
+    });
+    (result, inner)
+}
+
+/// Run `f`, and return its result together with all the storage changes it made.
+///
+/// A key written multiple times is reported once, with its value before the first write and
+/// after the last one.
+pub fn recorded<R>(f: impl FnOnce() -> R) -> (R, PalletChanges) {
+    let outer = CHANGES.with(|changes| changes.replace(Some(BTreeMap::new())));
+    let result = f();
+    let inner = CHANGES
+        .with(|changes| changes.replace(outer))
+        .unwrap_or_default();
+    let mut grouped = PalletChanges::new();
+    for ((pallet, _, _), change) in inner {
+        // Changes made by nested code are also changes made by the outer code.
+        let old = || change.old.clone();
+        note_change(
+            pallet,
+            change.item,
+            change.key.clone(),
+            old,
+            change.new.clone(),
+        );
+        grouped.entry(pallet).or_default().push(change);
+    }
+    (result, grouped)
+}
+
+fn is_recording() -> bool {
+    CHANGES.with(|changes| changes.borrow().is_some())
+}
+
+fn note_change(
+    pallet: &'static str,
+    item: &'static str,
+    key: Vec<u8>,
+    old: impl FnOnce() -> Option<Vec<u8>>,
+    new: Option<Vec<u8>>,
+) {
+    CHANGES.with(|changes| {
+        if let Some(changes) = changes.borrow_mut().as_mut() {
+            changes
+                .entry((pallet, item, key.clone()))
+                .or_insert_with(|| StorageChange {
+                    item,
+                    key,
+                    old: old(),
+                    new: None,
+                })
+                .new = new;
+        }
+    })
+}
+
+fn note(f: impl FnOnce(&mut StorageMeter)) {
+    METER.with(|meter| {
+        if let Some(meter) = meter.borrow_mut().as_mut() {
+            f(meter)
+        }
+    })
+}
+
+fn note_read(bytes: usize) {
+    note(|meter| {
+        meter.reads += 1;
+        meter.bytes_read += bytes as u64;
+    })
+}
+
+fn note_write(bytes: usize) {
+    note(|meter| {
+        meter.writes += 1;
+        meter.bytes_written += bytes as u64;
+    })
+}
+
+/// A map in storage. Every access is recorded by the surrounding `metered` and `recorded` calls,
+/// if any.
+///
+/// The map also keeps the values it held at the end of earlier blocks, so they can be read with
+/// `read_at`, until they are pruned.
+pub struct StorageMap<K, V> {
+    /// The name of the pallet owning this map.
+    pallet: &'static str,
+    /// The name of this map within its pallet.
+    item: &'static str,
+    inner: BTreeMap<K, V>,
+    /// For each key, the value it had before the first write of each block which wrote it.
+    history: BTreeMap<K, BTreeMap<u64, Option<V>>>,
+}
+
+impl<K: Ord + Clone + Encode, V: Encode> StorageMap<K, V> {
+    /// Create a new, empty storage map named `item`, owned by `pallet`.
+    pub fn new(pallet: &'static str, item: &'static str) -> Self {
+        Self {
+            pallet,
+            item,
+            inner: BTreeMap::new(),
+            history: BTreeMap::new(),
+        }
+    }
+
+    /// Get the value stored under `key`, if any.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let value = self.value(key, READ_AT.with(Cell::get));
+        note_read(key.encoded_size() + value.map_or(0, Encode::encoded_size));
+        value
+    }
+
+    /// Iterate over the entries of the map in key order, starting after the key `cursor`.
+    pub fn iter_from<'a>(
+        &'a self,
+        cursor: Option<&K>,
+    ) -> Box<dyn Iterator<Item = (&'a K, &'a V)> + 'a> {
+        let after = match cursor {
+            Some(cursor) => Bound::Excluded(cursor.clone()),
+            None => Bound::Unbounded,
+        };
+        let read_at = READ_AT.with(Cell::get);
+        let entries: Box<dyn Iterator<Item = (&'a K, &'a V)>> = match read_at {
+            // Keys which have been removed since are only left in the history.
+            Some(_) => {
+                let range = (after.clone(), Bound::Unbounded);
+                let keys: BTreeSet<&K> = self
+                    .inner
+                    .range(range.clone())
+                    .map(|(key, _)| key)
+                    .chain(self.history.range(range).map(|(key, _)| key))
+                    .collect();
+                Box::new(
+                    keys.into_iter()
+                        .filter_map(move |key| Some((key, self.value(key, read_at)?))),
+                )
+            }
+            None => Box::new(self.inner.range((after, Bound::Unbounded))),
+        };
+        Box::new(
+            entries.inspect(|(key, value)| note_read(key.encoded_size() + value.encoded_size())),
+        )
+    }
+
+    /// Get up to `limit` entries of the map in key order, starting after the key `cursor`.
+    pub fn page(&self, cursor: Option<&K>, limit: usize) -> Page<K, V>
+    where
+        V: Clone,
+    {
+        Page::new(self.iter_from(cursor), limit)
+    }
+
+    // The value stored under `key`, at the end of block `read_at` if given.
+    fn value(&self, key: &K, read_at: Option<u64>) -> Option<&V> {
+        match read_at {
+            // The value at the end of `number` is the one before the first write after it.
+            Some(number) => match self
+                .history
+                .get(key)
+                .and_then(|h| h.range(number + 1..).next())
+            {
+                Some((_, old)) => old.as_ref(),
+                None => self.inner.get(key),
+            },
+            None => self.inner.get(key),
+        }
+    }
+
+    /// Check whether a value is stored under `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Store `value` under `key`, replacing any previous value.
+    pub fn insert(&mut self, key: K, value: V) {
+        let encoded = value.encode();
+        note_write(key.encoded_size() + encoded.len());
+        self.note_change(&key, Some(encoded));
+        let old = self.inner.insert(key.clone(), value);
+        self.note_history(key, old);
+    }
+
+    /// Remove the value stored under `key`, if any.
+    pub fn remove(&mut self, key: &K) {
+        note_write(key.encoded_size());
+        self.note_change(key, None);
+        let old = self.inner.remove(key);
+        self.note_history(key.clone(), old);
+    }
+
+    fn note_history(&mut self, key: K, old: Option<V>) {
+        if let Some(number) = BLOCK.with(Cell::get) {
+            let history = self.history.entry(key).or_default();
+            history.entry(number).or_insert(old);
+        }
+    }
+
+    fn note_change(&self, key: &K, new: Option<Vec<u8>>) {
+        if is_recording() {
+            let old = || self.inner.get(key).map(Encode::encode);
+            note_change(self.pallet, self.item, key.encode(), old, new);
+        }
+    }
+}
+
+impl<K: Ord, V> Prune for StorageMap<K, V> {
+    fn prune(&mut self, earliest: u64) {
+        // The value before a write is only needed to read the blocks before it.
+        self.history.retain(|_, history| {
+            history.retain(|written_at, _| *written_at > earliest);
+            !history.is_empty()
+        });
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for StorageMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// A single value in storage, with the same recording and history as a `StorageMap`.
+pub struct StorageValue<V> {
+    map: StorageMap<(), V>,
+}
+
+impl<V: Encode> StorageValue<V> {
+    /// Create a new, empty storage value named `item`, owned by `pallet`.
+    pub fn new(pallet: &'static str, item: &'static str) -> Self {
+        Self {
+            map: StorageMap::new(pallet, item),
+        }
+    }
+
+    /// Get the stored value, if any.
+    pub fn get(&self) -> Option<&V> {
+        self.map.get(&())
+    }
+
+    /// Store `value`, replacing any previous value.
+    pub fn put(&mut self, value: V) {
+        self.map.insert((), value)
+    }
+}
+
+impl<V> Prune for StorageValue<V> {
+    fn prune(&mut self, earliest: u64) {
+        self.map.prune(earliest)
+    }
+}
+
+impl<V: Debug> Debug for StorageValue<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.inner.get(&()).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_works() {
+        assert_eq!(7u32.encode(), vec![7, 0, 0, 0]);
+        assert_eq!("hi".encode(), vec![2, 0, 0, 0, b'h', b'i']);
+        assert_eq!("hi".to_string().encoded_size(), 6);
+        assert_eq!([1u8, 2].encode(), vec![1, 2]);
+        assert_eq!(vec![1u8, 2].encode(), vec![2, 0, 0, 0, 1, 2]);
+        assert_eq!((1u8, true).encode(), vec![1, 1]);
+    }
+
+    #[test]
+    fn storage_map_is_metered() {
+        let mut map = StorageMap::<u32, u128>::new("test", "map");
+
+        let (_, meter) = metered(|| {
+            map.insert(1, 100);
+            assert_eq!(map.get(&1), Some(&100));
+            assert!(!map.contains_key(&2));
+            map.remove(&1);
+        });
+
+        assert_eq!(
+            meter,
+            StorageMeter {
+                reads: 2,
+                writes: 2,
+                bytes_read: 4 + 16 + 4,
+                bytes_written: 4 + 16 + 4
+            }
+        );
+
+        // Nothing is recorded outside of `metered`, and nested calls add up in the outer call.
+        assert_eq!(map.get(&1), None);
+        let ((_, inner), outer) = metered(|| metered(|| map.get(&1)));
+        assert_eq!(
+            inner,
+            StorageMeter {
+                reads: 1,
+                writes: 0,
+                bytes_read: 4,
+                bytes_written: 0
+            }
+        );
+        assert_eq!(outer, inner);
+    }
+
+    #[test]
+    fn weight_works() {
+        let meter = StorageMeter {
+            reads: 2,
+            writes: 1,
+            bytes_read: 10,
+            bytes_written: 5,
+        };
+        let db = DbWeight {
+            read: 25,
+            write: 100,
+            per_byte: 1,
+        };
+        assert_eq!(meter.weight(db), 2 * 25 + 100 + 15);
+    }
+
+    #[test]
+    fn storage_map_changes_are_recorded() {
+        let mut map = StorageMap::<u32, u32>::new("test", "map");
+        map.insert(1, 10);
+
+        let (_, changes) = recorded(|| {
+            map.insert(1, 11);
+            map.insert(1, 12);
+            map.insert(2, 20);
+            map.remove(&2);
+            // Reads are not changes.
+            map.get(&3);
+        });
+
+        let change = |key: u32, old: Option<u32>, new: Option<u32>| StorageChange {
+            item: "map",
+            key: key.encode(),
+            old: old.map(|v| v.encode()),
+            new: new.map(|v| v.encode()),
+        };
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes["test"],
+            vec![change(1, Some(10), Some(12)), change(2, None, None)]
+        );
+
+        // Nested changes are also reported to the outer call.
+        let ((_, inner), outer) = recorded(|| recorded(|| map.remove(&1)));
+        assert_eq!(inner["test"], vec![change(1, Some(12), None)]);
+        assert_eq!(outer, inner);
+    }
+
+    #[test]
+    fn storage_map_pages_work() {
+        let mut map = StorageMap::<u32, u32>::new("test", "map");
+        for key in 1..=5 {
+            map.insert(key, key * 10);
+        }
+
+        let (page, meter) = metered(|| map.page(None, 2));
+        assert_eq!(
+            page,
+            Page {
+                items: vec![(1, 10), (2, 20)],
+                next: Some(2)
+            }
+        );
+        assert_eq!(meter.reads, 3);
+
+        let page = map.page(page.next.as_ref(), 2);
+        assert_eq!(
+            page,
+            Page {
+                items: vec![(3, 30), (4, 40)],
+                next: Some(4)
+            }
+        );
+        let page = map.page(page.next.as_ref(), 2);
+        assert_eq!(
+            page,
+            Page {
+                items: vec![(5, 50)],
+                next: None
+            }
+        );
+
+        // The last page is not followed by an empty one.
+        assert_eq!(map.page(Some(&2), 3).next, None);
+        let even = Page::new(map.iter_from(None).filter(|(key, _)| *key % 2 == 0), 5);
+        assert_eq!(
+            even,
+            Page {
+                items: vec![(2, 20), (4, 40)],
+                next: None
+            }
+        );
+    }
+
+    #[test]
+    fn storage_value_works() {
+        let mut value = StorageValue::<u32>::new("test", "value");
+        assert_eq!(value.get(), None);
+
+        let (_, changes) = recorded(|| value.put(7));
+        assert_eq!(value.get(), Some(&7));
+        assert_eq!(
+            changes["test"],
+            vec![StorageChange {
+                item: "value",
+                key: vec![],
+                old: None,
+                new: Some(7u32.encode())
+            }]
+        );
+    }
+
+    #[test]
+    fn storage_map_reads_earlier_blocks() {
+        let mut map = StorageMap::<u32, u32>::new("test", "map");
+
+        // Writes outside of a block are part of the state as of every block.
+        map.insert(3, 30);
+        begin_block(1);
+        map.insert(1, 10);
+        begin_block(2);
+        map.insert(1, 11);
+        map.insert(1, 12);
+        map.insert(2, 20);
+        begin_block(3);
+        map.remove(&1);
+        end_block();
+
+        assert_eq!(read_at(0, || map.get(&1)), None);
+        assert_eq!(read_at(1, || map.get(&1)), Some(&10));
+        assert_eq!(read_at(2, || map.get(&1)), Some(&12));
+        assert_eq!(read_at(3, || map.get(&1)), None);
+        assert_eq!(read_at(1, || map.get(&2)), None);
+        assert_eq!(read_at(3, || map.get(&2)), Some(&20));
+        assert_eq!(map.get(&2), Some(&20));
+        assert_eq!(read_at(0, || map.get(&3)), Some(&30));
+
+        let at_2 = read_at(2, || map.page(None, 5));
+        assert_eq!(
+            at_2,
+            Page {
+                items: vec![(1, 12), (2, 20), (3, 30)],
+                next: None
+            }
+        );
+        assert_eq!(
+            map.page(None, 5),
+            Page {
+                items: vec![(2, 20), (3, 30)],
+                next: None
+            }
+        );
+    }
+
+    #[test]
+    fn storage_map_history_is_pruned() {
+        assert_eq!(Pruning::Archive.earliest(10), 0);
+        assert_eq!(Pruning::KeepLast(3).earliest(10), 7);
+        assert_eq!(Pruning::KeepLast(3).earliest(2), 0);
+
+        let mut map = StorageMap::<u32, u32>::new("test", "map");
+        begin_block(1);
+        map.insert(2, 1);
+        for number in 1..=10 {
+            begin_block(number);
+            map.prune(Pruning::KeepLast(3).earliest(number));
+            map.insert(1, number as u32);
+        }
+        end_block();
+
+        // Only the writes needed to read blocks 7 to 10 are kept, including for keys which were
+        // not written since.
+        assert_eq!(map.history[&1].len(), 3);
+        assert!(!map.history.contains_key(&2));
+        assert_eq!(read_at(7, || map.get(&1)), Some(&7));
+        assert_eq!(read_at(9, || map.get(&1)), Some(&9));
+    }
+}
@@ -1,25 +1,82 @@
 // use super::types;
+use crate::support::hashing::{Digest, Hashing};
+use crate::support::merkle;
+use crate::support::storage::{
+    Encode, Page, PalletChanges, Prune, Pruning, StorageMap, StorageValue,
+};
+use crate::support::DispatchResult;
+use core::fmt::Debug;
 use num::traits::{One, Zero};
-use std::collections::BTreeMap;
 use std::ops::AddAssign;
 
-/// This is the System Pallet.
-/// It handles low level state needed for your blockchain.
+// This is the System Pallet.
+// It handles low level state needed for your blockchain.
 
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + AddAssign + Copy;
-    type Nonce: Zero + One + Copy + AddAssign;
+    type AccountId: Ord + Clone + Encode;
+    type BlockNumber: Debug + Zero + One + AddAssign + Copy + PartialEq + Into<u64> + Encode;
+    type Nonce: Zero + One + Copy + AddAssign + Encode;
+    /// The events of all the pallets of the runtime.
+    type RuntimeEvent: Debug + Clone;
+    /// How many earlier blocks the state can still be queried at.
+    const PRUNING: Pruning;
+    /// How many of the most recent block hashes are kept. With 0, none is kept, not even the one
+    /// of the latest block, so the parent hash is always all zeros.
+    const BLOCK_HASH_COUNT: u64;
     // and more if needed
 }
-#[derive(Debug)]
+
+/// The hash function blocks are hashed with.
+const BLOCK_HASHING: Hashing = Hashing::Blake2b256;
+
+/// The information kept about each account.
 ///
+/// An account is kept alive by its providers and sufficients, and reaped once it has none left.
+/// It cannot be reaped while it has consumers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo<Nonce> {
+    /// The number of extrinsics the account has made.
+    pub nonce: Nonce,
+    /// The number of pallets keeping the account alive, e.g. because it has funds.
+    pub providers: u32,
+    /// The number of entries in other pallets depending on the account, e.g. its claims or its
+    /// reserved funds.
+    pub consumers: u32,
+    /// The number of entries keeping the account alive on their own, without any provider.
+    pub sufficients: u32,
+}
+
+impl<Nonce: Encode> Encode for AccountInfo<Nonce> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.nonce.encode();
+        bytes.extend(self.providers.encode());
+        bytes.extend(self.consumers.encode());
+        bytes.extend(self.sufficients.encode());
+        bytes
+    }
+}
+
+/// This is the System Pallet.
+/// It handles low level state needed for your blockchain.
+#[derive(Debug)]
 pub struct Pallet<T: Config> {
     /// The current block number.
     block_number: T::BlockNumber,
 
-    /// A map from an account to their nonce.
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    /// A map from an account to its nonce and reference counts.
+    account: StorageMap<T::AccountId, AccountInfo<T::Nonce>>,
+
+    /// The account allowed to make root-only calls, if any.
+    root: StorageValue<T::AccountId>,
+
+    /// The events deposited during the current block.
+    events: Vec<T::RuntimeEvent>,
+
+    /// A map from the number of each recent block to its hash.
+    block_hash: StorageMap<u64, Digest>,
+
+    /// The items logged during the current block, which its hash commits to.
+    digest: Vec<Digest>,
 }
 
 impl<T: Config> Pallet<T> {
@@ -27,7 +84,11 @@ impl<T: Config> Pallet<T> {
     pub fn new() -> Self {
         Self {
             block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::<T::AccountId, T::Nonce>::new(),
+            account: StorageMap::new("system", "account"),
+            root: StorageValue::new("system", "root"),
+            events: Vec::new(),
+            block_hash: StorageMap::new("system", "block_hash"),
+            digest: Vec::new(),
         }
     }
 
@@ -39,14 +100,112 @@ impl<T: Config> Pallet<T> {
 
     pub fn get_nonce(&self, who: &T::AccountId) -> T::Nonce {
         /* Return the current block number. */
-        *self.nonce.get(who).unwrap_or(&T::Nonce::zero())
+        self.account(who).nonce
+    }
+
+    /// Get up to `limit` account nonces, in account order, starting after the account `cursor`.
+    pub fn nonces(
+        &self,
+        cursor: Option<&T::AccountId>,
+        limit: usize,
+    ) -> Page<T::AccountId, T::Nonce> {
+        let nonces = self
+            .account
+            .iter_from(cursor)
+            .map(|(who, info)| (who, &info.nonce));
+        Page::new(nonces, limit)
+    }
+
+    /// The information kept about the account `who`, empty if it does not exist.
+    pub fn account(&self, who: &T::AccountId) -> AccountInfo<T::Nonce> {
+        self.account.get(who).cloned().unwrap_or(AccountInfo {
+            nonce: T::Nonce::zero(),
+            providers: 0,
+            consumers: 0,
+            sufficients: 0,
+        })
+    }
+
+    /// Set the account allowed to make root-only calls.
+    pub fn set_root(&mut self, who: &T::AccountId) {
+        self.root.put(who.clone());
+    }
+
+    /// Check that `who` is the account allowed to make root-only calls.
+    pub fn ensure_root(&self, who: &T::AccountId) -> DispatchResult {
+        match self.root.get() {
+            Some(root) if root == who => Ok(()),
+            _ => Err("caller is not root"),
+        }
+    }
+
+    /// Deposit an event, to let the outside world know something happened in this block.
+    pub fn deposit_event(&mut self, event: impl Into<T::RuntimeEvent>) {
+        self.events.push(event.into());
+    }
+
+    /// Get the events deposited during the current block.
+    pub fn events(&self) -> &[T::RuntimeEvent] {
+        &self.events
+    }
+
+    /// Clear the events of the previous block.
+    pub fn reset_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// Log `item` in the digest of the current block, so that its hash commits to it.
+    pub fn deposit_log(&mut self, item: Digest) {
+        self.digest.push(item);
+    }
+
+    /// Get the items logged during the current block.
+    pub fn digest(&self) -> &[Digest] {
+        &self.digest
+    }
+
+    /// Clear the digest of the previous block.
+    pub fn reset_digest(&mut self) {
+        self.digest.clear();
+    }
+
+    /// The hash of block `block_number`, if it is one of the last `BLOCK_HASH_COUNT` blocks
+    /// finalized.
+    pub fn block_hash(&self, block_number: T::BlockNumber) -> Option<Digest> {
+        self.block_hash.get(&block_number.into()).copied()
+    }
+
+    /// The hash of the block before the current one, all zeros for the first block.
+    pub fn parent_hash(&self) -> Digest {
+        let number: u64 = self.block_number.into();
+        number
+            .checked_sub(1)
+            .and_then(|parent| self.block_hash.get(&parent).copied())
+            .unwrap_or([0; 32])
+    }
+
+    /// Record the hash of the current block, which commits to its number, its parent, its digest
+    /// and the Merkle root of the storage `changes` made in it, and forget the hash of the block
+    /// leaving the window of `BLOCK_HASH_COUNT`.
+    pub fn finalize_block(&mut self, changes: &PalletChanges) {
+        let mut header = self.parent_hash().encode();
+        header.extend(self.block_number.encode());
+        header.extend(changes_root(changes).encode());
+        header.extend(self.digest.encode());
+        let number: u64 = self.block_number.into();
+        if T::BLOCK_HASH_COUNT > 0 {
+            self.block_hash.insert(number, BLOCK_HASHING.hash(&header));
+        }
+        if let Some(expired) = number.checked_sub(T::BLOCK_HASH_COUNT) {
+            self.block_hash.remove(&expired);
+        }
     }
 
     // This function can be used to increment the block number.
     // Increases the block number by one.
     pub fn inc_block_number(&mut self) {
         /* Increment the current block number by one. */
-        self.block_number = self.block_number + One::one();
+        self.block_number += One::one();
 
         // BlockNumber::checked_add(self.block_number, &1.into());
     }
@@ -55,20 +214,131 @@ impl<T: Config> Pallet<T> {
     // account has made.
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
         /* TODO: Get the current nonce of `who`, and increment it by one. */
-        let zero = T::Nonce::zero();
-        let old = self.nonce.get(who).unwrap_or(&zero);
-        self.nonce.insert(who.clone(), *old + One::one());
+        let mut info = self.account(who);
+        info.nonce += One::one();
+        self.account.insert(who.clone(), info);
+    }
+
+    /// Whether `who` has an account, which it gets by making its first extrinsic or being given
+    /// a provider or sufficient.
+    pub fn account_exists(&self, who: &T::AccountId) -> bool {
+        self.account.contains_key(who)
+    }
+
+    /// The number of pallets keeping the account `who` alive.
+    pub fn providers(&self, who: &T::AccountId) -> u32 {
+        self.account(who).providers
+    }
+
+    /// The number of entries in other pallets depending on the account `who`.
+    pub fn consumers(&self, who: &T::AccountId) -> u32 {
+        self.account(who).consumers
+    }
+
+    /// The number of entries keeping the account `who` alive on their own.
+    pub fn sufficients(&self, who: &T::AccountId) -> u32 {
+        self.account(who).sufficients
+    }
+
+    /// Record that a pallet keeps the account `who` alive, creating it if needed.
+    pub fn inc_providers(&mut self, who: &T::AccountId) {
+        let mut info = self.account(who);
+        info.providers = info.providers.saturating_add(1);
+        self.account.insert(who.clone(), info);
+    }
+
+    /// Record that a pallet no longer keeps the account `who` alive. The account is reaped if
+    /// nothing else keeps it alive, which fails if other entries still depend on it.
+    pub fn dec_providers(&mut self, who: &T::AccountId) -> DispatchResult {
+        let mut info = self.account(who);
+        info.providers = info.providers.saturating_sub(1);
+        self.settle_account(who, info)
+    }
+
+    /// Record that another pallet stored an entry depending on the account `who`, which must not
+    /// be reaped until the entry is removed.
+    pub fn inc_consumers(&mut self, who: &T::AccountId) {
+        let mut info = self.account(who);
+        info.consumers = info.consumers.saturating_add(1);
+        self.account.insert(who.clone(), info);
+    }
+
+    /// Record that an entry depending on the account `who` was removed.
+    pub fn dec_consumers(&mut self, who: &T::AccountId) {
+        if self.account_exists(who) {
+            let mut info = self.account(who);
+            info.consumers = info.consumers.saturating_sub(1);
+            self.account.insert(who.clone(), info);
+        }
+    }
+
+    /// Record that an entry keeps the account `who` alive on its own, creating it if needed.
+    pub fn inc_sufficients(&mut self, who: &T::AccountId) {
+        let mut info = self.account(who);
+        info.sufficients = info.sufficients.saturating_add(1);
+        self.account.insert(who.clone(), info);
+    }
+
+    /// Record that an entry no longer keeps the account `who` alive. The account is reaped if
+    /// nothing else keeps it alive, which fails if other entries still depend on it.
+    pub fn dec_sufficients(&mut self, who: &T::AccountId) -> DispatchResult {
+        let mut info = self.account(who);
+        info.sufficients = info.sufficients.saturating_sub(1);
+        self.settle_account(who, info)
+    }
+
+    /// Store `info` for the account `who`, or reap the account if it has no providers nor
+    /// sufficients left. Nothing is changed if it cannot be reaped, as it still has consumers.
+    fn settle_account(
+        &mut self,
+        who: &T::AccountId,
+        info: AccountInfo<T::Nonce>,
+    ) -> DispatchResult {
+        if info.providers > 0 || info.sufficients > 0 {
+            self.account.insert(who.clone(), info);
+        } else if info.consumers > 0 {
+            return Err("Account cannot be reaped while other entries depend on it.");
+        } else {
+            self.account.remove(who);
+        }
+        Ok(())
+    }
+}
+
+/// The Merkle root of the storage `changes` of a block, with a leaf for the new value of each key.
+fn changes_root(changes: &PalletChanges) -> Digest {
+    let leaves: Vec<Digest> = changes
+        .iter()
+        .flat_map(|(pallet, changes)| {
+            changes.iter().map(move |change| {
+                let leaf = ((*pallet, change.item), (&change.key, &change.new));
+                BLOCK_HASHING.hash(&leaf.encode())
+            })
+        })
+        .collect();
+    merkle::root(BLOCK_HASHING, &leaves)
+}
+
+impl<T: Config> Prune for Pallet<T> {
+    fn prune(&mut self, earliest: u64) {
+        self.account.prune(earliest);
+        self.root.prune(earliest);
+        self.block_hash.prune(earliest);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    struct testConfig;
-    impl Config for testConfig {
+    use crate::support::storage::StorageChange;
+    struct TestConfig;
+    impl Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = ();
+        const PRUNING: Pruning = Pruning::Archive;
+        const BLOCK_HASH_COUNT: u64 = 2;
     }
 
     #[test]
@@ -80,7 +350,7 @@ mod test {
             - Check the nonce of `alice` is what we expect.
         */
         // let testConfig = Config{AccountId, BlockNumber, Nonce};
-        let mut system = Pallet::<testConfig>::new();
+        let mut system = Pallet::<TestConfig>::new();
         system.inc_block_number();
         system.inc_nonce(&"Alice".to_string());
         system.inc_nonce(&"Alice".to_string());
@@ -89,4 +359,163 @@ mod test {
         assert_eq!(system.block_number(), 1);
         assert_eq!(system.get_nonce(&"Alice".to_string()), 3);
     }
+
+    #[test]
+    fn ensure_root_works() {
+        let mut system = Pallet::<TestConfig>::new();
+        assert_eq!(
+            system.ensure_root(&"Alice".to_string()),
+            Err("caller is not root")
+        );
+
+        system.set_root(&"Alice".to_string());
+        assert_eq!(system.ensure_root(&"Alice".to_string()), Ok(()));
+        assert_eq!(
+            system.ensure_root(&"Bob".to_string()),
+            Err("caller is not root")
+        );
+    }
+
+    #[test]
+    fn consumers_prevent_reaping() {
+        let mut system = Pallet::<TestConfig>::new();
+        let alice = "Alice".to_string();
+        assert!(!system.account_exists(&alice));
+        system.inc_nonce(&alice);
+        assert!(system.account_exists(&alice));
+
+        system.inc_providers(&alice);
+        system.inc_consumers(&alice);
+        system.inc_consumers(&alice);
+        assert_eq!(system.consumers(&alice), 2);
+        assert_eq!(
+            system.dec_providers(&alice),
+            Err("Account cannot be reaped while other entries depend on it.")
+        );
+        assert_eq!(system.providers(&alice), 1);
+        system.dec_consumers(&alice);
+        system.dec_consumers(&alice);
+        assert_eq!(system.dec_providers(&alice), Ok(()));
+        assert!(!system.account_exists(&alice));
+        assert_eq!(system.get_nonce(&alice), 0);
+    }
+
+    #[test]
+    fn providers_and_sufficients_keep_accounts_alive() {
+        let mut system = Pallet::<TestConfig>::new();
+        let alice = "Alice".to_string();
+        system.inc_providers(&alice);
+        system.inc_sufficients(&alice);
+        system.inc_nonce(&alice);
+        assert_eq!(
+            system.account(&alice),
+            AccountInfo {
+                nonce: 1,
+                providers: 1,
+                consumers: 0,
+                sufficients: 1
+            }
+        );
+
+        // The account lives on as long as anything keeps it alive.
+        assert_eq!(system.dec_providers(&alice), Ok(()));
+        assert!(system.account_exists(&alice));
+        assert_eq!(system.get_nonce(&alice), 1);
+        system.inc_consumers(&alice);
+        assert_eq!(
+            system.dec_sufficients(&alice),
+            Err("Account cannot be reaped while other entries depend on it.")
+        );
+        system.dec_consumers(&alice);
+        assert_eq!(system.dec_sufficients(&alice), Ok(()));
+        assert!(!system.account_exists(&alice));
+    }
+
+    #[test]
+    fn block_hashes_are_kept_for_a_window() {
+        let mut system = Pallet::<TestConfig>::new();
+        let mut hashes = vec![];
+        for block in 1..=3u8 {
+            system.inc_block_number();
+            system.reset_digest();
+            system.deposit_log([block; 32]);
+            assert_eq!(system.digest(), &[[block; 32]]);
+            assert_eq!(
+                system.parent_hash(),
+                hashes.last().copied().unwrap_or([0; 32])
+            );
+            system.finalize_block(&PalletChanges::new());
+            hashes.extend(system.block_hash(block.into()));
+        }
+        assert_eq!(hashes.len(), 3);
+        assert_ne!(hashes[0], hashes[1]);
+
+        // Only the hashes of the last two blocks are kept.
+        assert_eq!(system.block_hash(1), None);
+        assert_eq!(system.block_hash(2), Some(hashes[1]));
+        assert_eq!(system.block_hash(3), Some(hashes[2]));
+        assert_eq!(system.parent_hash(), hashes[1]);
+        assert_eq!(system.block_hash(4), None);
+    }
+
+    #[test]
+    fn block_hashes_commit_to_storage_changes() {
+        let change = |new: u8| StorageChange {
+            item: "item",
+            key: vec![0],
+            old: None,
+            new: Some(vec![new]),
+        };
+        let hashes: Vec<_> = [0, 1]
+            .into_iter()
+            .map(|new| {
+                let mut system = Pallet::<TestConfig>::new();
+                system.inc_block_number();
+                system.finalize_block(&PalletChanges::from([("pallet", vec![change(new)])]));
+                system.block_hash(1)
+            })
+            .collect();
+        assert!(hashes[0].is_some());
+        assert_ne!(hashes[0], hashes[1]);
+    }
+
+    #[test]
+    fn no_block_hashes_are_kept_without_a_window() {
+        struct NoHashes;
+        impl Config for NoHashes {
+            type AccountId = String;
+            type BlockNumber = u32;
+            type Nonce = u32;
+            type RuntimeEvent = ();
+            const PRUNING: Pruning = Pruning::Archive;
+            const BLOCK_HASH_COUNT: u64 = 0;
+        }
+
+        let mut system = Pallet::<NoHashes>::new();
+        for _ in 0..2 {
+            system.inc_block_number();
+            system.finalize_block(&PalletChanges::new());
+        }
+        assert_eq!(system.block_hash(1), None);
+        assert_eq!(system.block_hash(2), None);
+        assert_eq!(system.parent_hash(), [0; 32]);
+    }
+
+    #[test]
+    fn nonces_are_paginated() {
+        let mut system = Pallet::<TestConfig>::new();
+        system.inc_nonce(&"Alice".to_string());
+        system.inc_nonce(&"Bob".to_string());
+        system.inc_nonce(&"Bob".to_string());
+        system.inc_nonce(&"Charlie".to_string());
+
+        let page = system.nonces(None, 2);
+        assert_eq!(
+            page.items,
+            vec![("Alice".to_string(), 1), ("Bob".to_string(), 2)]
+        );
+        let page = system.nonces(page.next.as_ref(), 2);
+        assert_eq!(page.items, vec![("Charlie".to_string(), 1)]);
+        assert_eq!(page.next, None);
+    }
 }
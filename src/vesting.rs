@@ -0,0 +1,459 @@
+use crate::support::currency::{Currency, LockIdentifier, LockableCurrency, Reasons};
+use crate::support::storage::{Encode, Prune, StorageMap};
+use crate::support::DispatchResult;
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Zero};
+
+/// The name of the lock this pallet puts on the funds still vesting.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: crate::system::Config + Sized + AsRef<crate::system::Pallet<Self>> {
+    type Balance: Debug
+        + Zero
+        + One
+        + Copy
+        + PartialOrd
+        + CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + CheckedDiv
+        + From<u64>
+        + Encode;
+    /// The currency in which funds vest, which must be able to lock them.
+    type Currency: LockableCurrency<Self, AccountId = Self::AccountId, Balance = Self::Balance>;
+    /// The maximum number of vesting schedules an account can have.
+    const MAX_SCHEDULES: usize;
+    /// The minimum amount a vested transfer can lock, so that an account cannot be filled with
+    /// tiny schedules leaving no room for real ones.
+    const MIN_VESTED_TRANSFER: Self::Balance;
+}
+
+/// A schedule unlocking funds linearly, block after block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+    /// The amount locked when the schedule starts.
+    pub locked: Balance,
+    /// The amount unlocked at every block after the start.
+    pub per_block: Balance,
+    /// The block at which funds start to unlock.
+    pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+    Balance: Zero + One + Copy + PartialOrd + CheckedAdd + CheckedSub + CheckedMul + CheckedDiv,
+    Balance: From<u64>,
+    BlockNumber: Copy + Into<u64>,
+{
+    /// The amount still locked at block `n`.
+    pub fn locked_at(&self, n: BlockNumber) -> Balance {
+        let elapsed = n.into().saturating_sub(self.starting_block.into());
+        Balance::from(elapsed)
+            .checked_mul(&self.per_block)
+            .and_then(|vested| self.locked.checked_sub(&vested))
+            .unwrap_or_else(Balance::zero)
+    }
+
+    /// The block at which all the funds are unlocked.
+    fn ending_block(&self) -> Balance {
+        let start = Balance::from(self.starting_block.into());
+        let blocks = self
+            .locked
+            .checked_div(&self.per_block)
+            .unwrap_or_else(Balance::zero);
+        let blocks = if blocks.checked_mul(&self.per_block) == Some(self.locked) {
+            blocks
+        } else {
+            blocks + Balance::one()
+        };
+        start.checked_add(&blocks).unwrap_or(start)
+    }
+}
+
+impl<Balance: Encode, BlockNumber: Copy + Into<u64>> Encode for VestingInfo<Balance, BlockNumber> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.locked.encode();
+        bytes.extend(self.per_block.encode());
+        bytes.extend(self.starting_block.into().encode());
+        bytes
+    }
+}
+
+/// The vesting schedule type of a runtime.
+pub type VestingInfoOf<T> =
+    VestingInfo<<T as Config>::Balance, <T as crate::system::Config>::BlockNumber>;
+
+/// This is the Vesting Module.
+/// It locks funds granted to an account, and unlocks them over time following vesting schedules.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The vesting schedules of each account.
+    vesting: StorageMap<T::AccountId, Vec<VestingInfoOf<T>>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+    /// Unlock the funds of the `caller` which have vested by now.
+    pub fn vest(&mut self, runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        if !self.vesting.contains_key(&caller) {
+            return Err("Account has no vesting schedule.");
+        }
+        self.update_lock(runtime, &caller, self.schedules(&caller));
+        Ok(())
+    }
+
+    /// Transfer `schedule.locked` from the `caller` to `target`, locked under `schedule`.
+    pub fn vested_transfer(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        target: T::AccountId,
+        schedule: VestingInfoOf<T>,
+    ) -> DispatchResult {
+        if schedule.locked.is_zero() || schedule.per_block.is_zero() {
+            return Err("Invalid vesting schedule.");
+        }
+        if schedule.locked < T::MIN_VESTED_TRANSFER {
+            return Err("Vested transfer is below the minimum.");
+        }
+        let mut schedules = self.schedules(&target);
+        if schedules.len() >= T::MAX_SCHEDULES {
+            return Err("Too many vesting schedules.");
+        }
+
+        T::Currency::transfer(runtime, &caller, &target, schedule.locked, false)?;
+        schedules.push(schedule);
+        self.update_lock(runtime, &target, schedules);
+        Ok(())
+    }
+
+    /// Merge the schedules at `schedule1_index` and `schedule2_index` of the `caller` into one,
+    /// which unlocks what is still locked by both from now on, and never faster than they do.
+    pub fn merge_schedules(
+        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        schedule1_index: u32,
+        schedule2_index: u32,
+    ) -> DispatchResult {
+        let (index1, index2) = (schedule1_index as usize, schedule2_index as usize);
+        let mut schedules = self.schedules(&caller);
+        if index1 == index2 || index1.max(index2) >= schedules.len() {
+            return Err("Invalid schedule index.");
+        }
+
+        let now = block_number(runtime);
+        let schedule2 = schedules.remove(index1.max(index2));
+        let schedule1 = schedules.remove(index1.min(index2));
+        if let Some(merged) = merge(schedule1, schedule2, now)? {
+            schedules.push(merged);
+        }
+        self.update_lock(runtime, &caller, schedules);
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T> for Pallet<T> {}
+
+impl<T: Config> Prune for Pallet<T> {
+    fn prune(&mut self, earliest: u64) {
+        self.vesting.prune(earliest);
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the vesting module.
+    pub fn new() -> Self {
+        Self {
+            vesting: StorageMap::new("vesting", "vesting"),
+        }
+    }
+
+    /// The vesting schedules of `who`.
+    pub fn schedules(&self, who: &T::AccountId) -> Vec<VestingInfoOf<T>> {
+        self.vesting.get(who).cloned().unwrap_or_default()
+    }
+
+    /// The funds of `who` still locked at block `n`.
+    pub fn locked_at(&self, who: &T::AccountId, n: T::BlockNumber) -> T::Balance {
+        let mut total = T::Balance::zero();
+        for schedule in self.vesting.get(who).into_iter().flatten() {
+            total = total.checked_add(&schedule.locked_at(n)).unwrap_or(total);
+        }
+        total
+    }
+
+    /// Store the `schedules` of `who` which are still vesting, and lock what they still lock.
+    fn update_lock(
+        &mut self,
+        runtime: &mut T,
+        who: &T::AccountId,
+        mut schedules: Vec<VestingInfoOf<T>>,
+    ) {
+        let now = block_number(runtime);
+        schedules.retain(|schedule| !schedule.locked_at(now).is_zero());
+        if schedules.is_empty() {
+            self.vesting.remove(who);
+            T::Currency::remove_lock(runtime, VESTING_ID, who);
+        } else {
+            self.vesting.insert(who.clone(), schedules);
+            let locked = self.locked_at(who, now);
+            T::Currency::set_lock(runtime, VESTING_ID, who, locked, Reasons::All);
+        }
+    }
+}
+
+/// The current block number of the `runtime`.
+fn block_number<T: Config>(runtime: &T) -> T::BlockNumber {
+    let system: &crate::system::Pallet<T> = runtime.as_ref();
+    system.block_number()
+}
+
+/// Merge two schedules at block `now`, into one unlocking what both still lock, from `now` or
+/// their latest start, never faster than both. Returns `None` if both have fully vested.
+fn merge<Balance, BlockNumber>(
+    schedule1: VestingInfo<Balance, BlockNumber>,
+    schedule2: VestingInfo<Balance, BlockNumber>,
+    now: BlockNumber,
+) -> Result<Option<VestingInfo<Balance, BlockNumber>>, &'static str>
+where
+    Balance: Zero + One + Copy + PartialOrd + CheckedAdd + CheckedSub + CheckedMul + CheckedDiv,
+    Balance: From<u64>,
+    BlockNumber: Copy + Into<u64>,
+{
+    let locked = schedule1
+        .locked_at(now)
+        .checked_add(&schedule2.locked_at(now))
+        .ok_or("over/underflow occured.")?;
+    if locked.is_zero() {
+        return Ok(None);
+    }
+
+    let starting_block = [schedule1.starting_block, schedule2.starting_block]
+        .into_iter()
+        .fold(now, |latest, start| {
+            if start.into() > latest.into() {
+                start
+            } else {
+                latest
+            }
+        });
+    let (end1, end2) = (schedule1.ending_block(), schedule2.ending_block());
+    let end = if end1 > end2 { end1 } else { end2 };
+    let duration = end
+        .checked_sub(&Balance::from(starting_block.into()))
+        .unwrap_or_else(Balance::zero);
+    // Rounded down, so that the merged schedule never locks less than the two at any block, even
+    // if it ends a little later than they do.
+    let per_block = match locked.checked_div(&duration) {
+        Some(per_block) if per_block.is_zero() => Balance::one(),
+        Some(per_block) => per_block,
+        None => locked,
+    };
+
+    Ok(Some(VestingInfo {
+        locked,
+        per_block,
+        starting_block,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::balances;
+
+    struct TestRuntime {
+        system: crate::system::Pallet<TestRuntime>,
+        balances: balances::Pallet<TestRuntime>,
+    }
+
+    impl TestRuntime {
+        fn new() -> Self {
+            Self {
+                system: crate::system::Pallet::new(),
+                balances: balances::Pallet::new(),
+            }
+        }
+    }
+
+    impl AsRef<crate::system::Pallet<TestRuntime>> for TestRuntime {
+        fn as_ref(&self) -> &crate::system::Pallet<TestRuntime> {
+            &self.system
+        }
+    }
+
+    impl AsMut<crate::system::Pallet<TestRuntime>> for TestRuntime {
+        fn as_mut(&mut self) -> &mut crate::system::Pallet<TestRuntime> {
+            &mut self.system
+        }
+    }
+
+    impl AsRef<balances::Pallet<TestRuntime>> for TestRuntime {
+        fn as_ref(&self) -> &balances::Pallet<TestRuntime> {
+            &self.balances
+        }
+    }
+
+    impl AsMut<balances::Pallet<TestRuntime>> for TestRuntime {
+        fn as_mut(&mut self) -> &mut balances::Pallet<TestRuntime> {
+            &mut self.balances
+        }
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = &'static str;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = balances::Event<&'static str, u64>;
+        const PRUNING: crate::support::storage::Pruning = crate::support::storage::Pruning::Archive;
+        const BLOCK_HASH_COUNT: u64 = 256;
+    }
+
+    impl balances::Config for TestRuntime {
+        type Balance = u64;
+        const EXISTENTIAL_DEPOSIT: u64 = 1;
+        type RuntimeHoldReason = ();
+        type RuntimeFreezeReason = ();
+    }
+
+    impl Config for TestRuntime {
+        type Balance = u64;
+        type Currency = balances::Pallet<TestRuntime>;
+        const MAX_SCHEDULES: usize = 2;
+        const MIN_VESTED_TRANSFER: u64 = 5;
+    }
+
+    fn schedule(locked: u64, per_block: u64, starting_block: u32) -> VestingInfoOf<TestRuntime> {
+        VestingInfo {
+            locked,
+            per_block,
+            starting_block,
+        }
+    }
+
+    /// Create a runtime where `alice` has 100 and `root` can mint.
+    fn new_runtime() -> TestRuntime {
+        let mut runtime = TestRuntime::new();
+        runtime.system.set_root(&"root");
+        let mut balances = core::mem::replace(&mut runtime.balances, balances::Pallet::new());
+        balances.mint(&mut runtime, "root", "alice", 100).unwrap();
+        runtime.balances = balances;
+        runtime
+    }
+
+    #[test]
+    fn schedules_unlock_linearly() {
+        let info = schedule(100, 10, 5);
+        assert_eq!(info.locked_at(0), 100);
+        assert_eq!(info.locked_at(5), 100);
+        assert_eq!(info.locked_at(6), 90);
+        assert_eq!(info.locked_at(14), 10);
+        assert_eq!(info.locked_at(15), 0);
+        assert_eq!(info.locked_at(u32::MAX), 0);
+        assert_eq!(info.ending_block(), 15);
+        assert_eq!(schedule(95, 10, 5).ending_block(), 15);
+    }
+
+    #[test]
+    fn vested_transfer_locks_funds() {
+        let mut runtime = new_runtime();
+        let mut vesting = Pallet::<TestRuntime>::new();
+
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(50, 0, 2));
+        assert_eq!(result, Err("Invalid vesting schedule."));
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(4, 1, 2));
+        assert_eq!(result, Err("Vested transfer is below the minimum."));
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(200, 10, 2));
+        assert_eq!(result, Err("Not enough funds."));
+        assert_eq!(
+            vesting.vest(&mut runtime, "bob"),
+            Err("Account has no vesting schedule.")
+        );
+
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(50, 10, 2));
+        assert_eq!(result, Ok(()));
+        assert_eq!(runtime.balances.balance(&"bob"), 50);
+        assert_eq!(
+            runtime.balances.frozen_balance(&"bob", Reasons::Transfer),
+            50
+        );
+        let mut balances = core::mem::replace(&mut runtime.balances, balances::Pallet::new());
+        let result = balances.transfer(&mut runtime, "bob", "alice", 1);
+        assert_eq!(result, Err("Balance is locked."));
+        runtime.balances = balances;
+
+        // Funds unlock as blocks go by, once vested.
+        for _ in 0..4 {
+            runtime.system.inc_block_number();
+        }
+        assert_eq!(vesting.locked_at(&"bob", 4), 30);
+        assert_eq!(
+            runtime.balances.frozen_balance(&"bob", Reasons::Transfer),
+            50
+        );
+        assert_eq!(vesting.vest(&mut runtime, "bob"), Ok(()));
+        assert_eq!(
+            runtime.balances.frozen_balance(&"bob", Reasons::Transfer),
+            30
+        );
+
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(10, 1, 0));
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            runtime.balances.frozen_balance(&"bob", Reasons::Transfer),
+            36
+        );
+        let result = vesting.vested_transfer(&mut runtime, "alice", "bob", schedule(10, 1, 0));
+        assert_eq!(result, Err("Too many vesting schedules."));
+
+        // Schedules which have fully vested are removed, along with the lock.
+        for _ in 0..6 {
+            runtime.system.inc_block_number();
+        }
+        assert_eq!(vesting.vest(&mut runtime, "bob"), Ok(()));
+        assert_eq!(vesting.schedules(&"bob"), vec![]);
+        assert_eq!(runtime.balances.locks(&"bob"), vec![]);
+    }
+
+    #[test]
+    fn merge_schedules() {
+        let mut runtime = new_runtime();
+        let mut vesting = Pallet::<TestRuntime>::new();
+        vesting
+            .vested_transfer(&mut runtime, "alice", "bob", schedule(20, 2, 2))
+            .unwrap();
+        vesting
+            .vested_transfer(&mut runtime, "alice", "bob", schedule(30, 1, 10))
+            .unwrap();
+
+        let result = vesting.merge_schedules(&mut runtime, "bob", 0, 0);
+        assert_eq!(result, Err("Invalid schedule index."));
+        let result = vesting.merge_schedules(&mut runtime, "bob", 0, 2);
+        assert_eq!(result, Err("Invalid schedule index."));
+
+        // At block 7, the first schedule still locks 10 until block 12, and the second 30 from
+        // block 10 until block 40. The merged schedule unlocks the 40 from block 10, never faster
+        // than the two.
+        for _ in 0..7 {
+            runtime.system.inc_block_number();
+        }
+        let originals = vesting.schedules(&"bob");
+        assert_eq!(vesting.merge_schedules(&mut runtime, "bob", 1, 0), Ok(()));
+        let merged = schedule(40, 1, 10);
+        assert_eq!(vesting.schedules(&"bob"), vec![merged]);
+        assert_eq!(merged.ending_block(), 50);
+        for n in 7..=50 {
+            let locked: u64 = originals.iter().map(|schedule| schedule.locked_at(n)).sum();
+            assert!(
+                merged.locked_at(n) >= locked,
+                "unlocked early at block {}",
+                n
+            );
+        }
+        assert_eq!(
+            runtime.balances.frozen_balance(&"bob", Reasons::Transfer),
+            40
+        );
+    }
+}